@@ -0,0 +1,310 @@
+use std::sync::LazyLock;
+
+use crate::{bitboard::BitBoard, piece::Color, square::Square};
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+	(-1, -2),
+	(1, -2),
+	(-2, -1),
+	(2, -1),
+	(-2, 1),
+	(2, 1),
+	(-1, 2),
+	(1, 2),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+	(-1, -1),
+	(0, -1),
+	(1, -1),
+	(-1, 0),
+	(1, 0),
+	(-1, 1),
+	(0, 1),
+	(1, 1),
+];
+
+const KNIGHT_ATTACKS: [u64; 64] = step_table(&KNIGHT_OFFSETS);
+const KING_ATTACKS: [u64; 64] = step_table(&KING_OFFSETS);
+/// Rows grow downwards, so white pawns attack towards smaller rows
+const WHITE_PAWN_ATTACKS: [u64; 64] = step_table(&[(-1, -1), (1, -1)]);
+const BLACK_PAWN_ATTACKS: [u64; 64] = step_table(&[(-1, 1), (1, 1)]);
+
+static ROOK_TABLE: LazyLock<SliderTable> = LazyLock::new(|| SliderTable::new(&ROOK_DIRECTIONS));
+static BISHOP_TABLE: LazyLock<SliderTable> = LazyLock::new(|| SliderTable::new(&BISHOP_DIRECTIONS));
+
+/// Moves `dx` columns and `dy` rows away from `square`, as long as that stays on the board
+const fn offset(square: u8, dx: i8, dy: i8) -> Option<u8> {
+	let x = (square % 8) as i8 + dx;
+	let y = (square / 8) as i8 + dy;
+	if x >= 0 && x < 8 && y >= 0 && y < 8 {
+		Some(y as u8 * 8 + x as u8)
+	} else {
+		None
+	}
+}
+
+const fn step_table(offsets: &[(i8, i8)]) -> [u64; 64] {
+	let mut table = [0; 64];
+	let mut square = 0;
+	while square < 64 {
+		let mut j = 0;
+		while j < offsets.len() {
+			if let Some(target) = offset(square, offsets[j].0, offsets[j].1) {
+				table[square as usize] |= 1 << target;
+			}
+			j += 1;
+		}
+		square += 1;
+	}
+	table
+}
+
+/// Walks every direction until it leaves the board or hits a piece in `occupation`. The blocking square is included.
+/// Too slow for move generation, it is only used to fill the lookup tables
+fn walk_rays(square: u8, occupation: u64, directions: &[(i8, i8)]) -> u64 {
+	let mut attacks = 0;
+	for &(dx, dy) in directions {
+		let mut current = square;
+		while let Some(next) = offset(current, dx, dy) {
+			attacks |= 1 << next;
+			if occupation & 1 << next != 0 {
+				break;
+			}
+			current = next;
+		}
+	}
+	attacks
+}
+
+/// The squares whose occupation can change what a slider on `square` attacks.
+/// The last square of every ray is left out, since there is nothing behind it to block
+fn relevant_occupancy(square: u8, directions: &[(i8, i8)]) -> u64 {
+	let mut mask = 0;
+	for &(dx, dy) in directions {
+		let mut current = square;
+		while let Some(next) = offset(current, dx, dy) {
+			if offset(next, dx, dy).is_none() {
+				break;
+			}
+			mask |= 1 << next;
+			current = next;
+		}
+	}
+	mask
+}
+
+/// Maps the relevant occupation of one square onto its slice of [`SliderTable::attacks`] with a single multiplication
+#[derive(Clone, Copy, Debug)]
+struct Magic {
+	mask: u64,
+	magic: u64,
+	shift: u32,
+	offset: usize,
+}
+
+impl Magic {
+	fn index(&self, occupation: u64) -> usize {
+		self.offset + ((occupation & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+	}
+}
+
+struct SliderTable {
+	magics: Vec<Magic>,
+	attacks: Vec<u64>,
+}
+
+impl SliderTable {
+	/// Searches a magic number for every square.
+	/// The random numbers start from a fixed seed, so the tables come out the same on every run
+	fn new(directions: &[(i8, i8)]) -> Self {
+		let mut rng = 0x9E37_79B9_7F4A_7C15_u64;
+		let mut random = move || {
+			// xorshift64
+			rng ^= rng << 13;
+			rng ^= rng >> 7;
+			rng ^= rng << 17;
+			rng
+		};
+
+		let mut magics = Vec::with_capacity(64);
+		let mut attacks = Vec::new();
+
+		for square in 0..64 {
+			let mask = relevant_occupancy(square, directions);
+			let bits = mask.count_ones();
+
+			// every subset of the mask, enumerated with the carry-rippler trick
+			let mut occupancies = Vec::with_capacity(1 << bits);
+			let mut subset = 0_u64;
+			loop {
+				occupancies.push((subset, walk_rays(square, subset, directions)));
+				subset = subset.wrapping_sub(mask) & mask;
+				if subset == 0 {
+					break;
+				}
+			}
+
+			let mut table = vec![0; 1 << bits];
+			// which attempt last wrote an entry, so the table doesn't need to be cleared between attempts
+			let mut written = vec![0_u32; 1 << bits];
+			let mut attempt = 0;
+
+			let magic = loop {
+				// magics with few set bits work best
+				let candidate = random() & random() & random();
+				if (mask.wrapping_mul(candidate) >> 56).count_ones() < 6 {
+					continue;
+				}
+
+				attempt += 1;
+				let magic = Magic {
+					mask,
+					magic: candidate,
+					shift: 64 - bits,
+					offset: 0,
+				};
+				// two occupations may share an entry, but only if they lead to the same attacks
+				let fits = occupancies.iter().all(|&(occupation, reference)| {
+					let index = magic.index(occupation);
+					if written[index] != attempt {
+						written[index] = attempt;
+						table[index] = reference;
+						true
+					} else {
+						table[index] == reference
+					}
+				});
+
+				if fits {
+					break magic;
+				}
+			};
+
+			magics.push(Magic {
+				offset: attacks.len(),
+				..magic
+			});
+			attacks.extend(table);
+		}
+
+		Self { magics, attacks }
+	}
+
+	fn get(&self, square: Square, occupation: BitBoard) -> BitBoard {
+		let magic = &self.magics[square.index() as usize];
+		BitBoard::from(self.attacks[magic.index(u64::from(occupation))])
+	}
+}
+
+/// Builds the slider tables now instead of on first use, which would charge the time it takes to the first search
+pub fn init() {
+	LazyLock::force(&ROOK_TABLE);
+	LazyLock::force(&BISHOP_TABLE);
+}
+
+pub fn knight_attacks(square: Square) -> BitBoard {
+	BitBoard::from(KNIGHT_ATTACKS[square.index() as usize])
+}
+
+pub fn king_attacks(square: Square) -> BitBoard {
+	BitBoard::from(KING_ATTACKS[square.index() as usize])
+}
+
+/// The squares a pawn of `col` on `square` captures on
+pub fn pawn_attacks(square: Square, col: Color) -> BitBoard {
+	let table = match col {
+		Color::White => &WHITE_PAWN_ATTACKS,
+		Color::Black => &BLACK_PAWN_ATTACKS,
+	};
+	BitBoard::from(table[square.index() as usize])
+}
+
+/// The squares a bishop on `square` attacks. Pieces in `occupation` block it, and the blocking square is included
+pub fn bishop_attacks(square: Square, occupation: BitBoard) -> BitBoard {
+	BISHOP_TABLE.get(square, occupation)
+}
+
+/// The squares a rook on `square` attacks. Pieces in `occupation` block it, and the blocking square is included
+pub fn rook_attacks(square: Square, occupation: BitBoard) -> BitBoard {
+	ROOK_TABLE.get(square, occupation)
+}
+
+pub fn queen_attacks(square: Square, occupation: BitBoard) -> BitBoard {
+	bishop_attacks(square, occupation).union(rook_attacks(square, occupation))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		attacks::{
+			BISHOP_DIRECTIONS, ROOK_DIRECTIONS, bishop_attacks, king_attacks, knight_attacks,
+			pawn_attacks, rook_attacks, walk_rays,
+		},
+		bitboard::BitBoard,
+		piece::Color,
+		square::Square,
+	};
+
+	fn squares(attacks: BitBoard) -> Vec<Square> {
+		attacks.into_iter().collect()
+	}
+
+	#[test]
+	fn test_step_attacks() {
+		// corners must not wrap around to the other side of the board
+		assert_eq!(
+			squares(knight_attacks(Square::A8)),
+			vec![Square::C7, Square::B6]
+		);
+		assert_eq!(
+			squares(knight_attacks(Square::H1)),
+			vec![Square::G3, Square::F2]
+		);
+		assert_eq!(
+			squares(king_attacks(Square::H8)),
+			vec![Square::G8, Square::G7, Square::H7]
+		);
+		assert_eq!(knight_attacks(Square::E4).size(), 8);
+		assert_eq!(king_attacks(Square::E4).size(), 8);
+
+		assert_eq!(
+			squares(pawn_attacks(Square::A2, Color::White)),
+			vec![Square::B3]
+		);
+		assert_eq!(
+			squares(pawn_attacks(Square::H7, Color::Black)),
+			vec![Square::G6]
+		);
+		assert_eq!(
+			squares(pawn_attacks(Square::A7, Color::White)),
+			vec![Square::B8]
+		);
+	}
+
+	#[test]
+	fn test_slider_attacks() {
+		// an empty board
+		assert_eq!(rook_attacks(Square::A8, BitBoard::new()).size(), 14);
+		assert_eq!(bishop_attacks(Square::E4, BitBoard::new()).size(), 13);
+
+		// compare against walking the rays for a few pseudo random occupations
+		let mut occupation = 0x0123_4567_89AB_CDEF_u64;
+		for _ in 0..64 {
+			occupation = occupation
+				.rotate_left(7)
+				.wrapping_mul(0x2545_F491_4F6C_DD1D);
+			for square in Square::all() {
+				assert_eq!(
+					u64::from(rook_attacks(square, BitBoard::from(occupation))),
+					walk_rays(square.index(), occupation, &ROOK_DIRECTIONS)
+				);
+				assert_eq!(
+					u64::from(bishop_attacks(square, BitBoard::from(occupation))),
+					walk_rays(square.index(), occupation, &BISHOP_DIRECTIONS)
+				);
+			}
+		}
+	}
+}
@@ -1,28 +1,166 @@
+use std::ops::{
+	BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, ShlAssign, Shr,
+	ShrAssign,
+};
+
 use serde::Serialize;
 
+use crate::square::Square;
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct BitBoard {
 	hash: u64,
 }
 
+/// Square `i` is bit `i`, so a8 is the lowest bit and h1 the highest.
+/// North points towards the 8th rank, which means towards lower squares
 impl BitBoard {
-	pub fn new() -> BitBoard {
+	pub const EMPTY: BitBoard = BitBoard { hash: 0 };
+	pub const FULL: BitBoard = BitBoard { hash: u64::MAX };
+
+	/// The files a to h
+	pub const FILES: [BitBoard; 8] = {
+		let mut files = [Self::EMPTY; 8];
+		let mut file = 0;
+		while file < 8 {
+			files[file].hash = 0x0101_0101_0101_0101 << file;
+			file += 1;
+		}
+		files
+	};
+
+	/// The ranks 1 to 8
+	pub const RANKS: [BitBoard; 8] = {
+		let mut ranks = [Self::EMPTY; 8];
+		let mut rank = 0;
+		while rank < 8 {
+			ranks[rank].hash = 0xFF << (8 * (7 - rank));
+			rank += 1;
+		}
+		ranks
+	};
+
+	/// The diagonals running from a1 towards h8, starting with the single square a8 and ending with h1
+	pub const DIAGONALS: [BitBoard; 15] = {
+		let mut diagonals = [Self::EMPTY; 15];
+		let mut i = 0;
+		while i < 64 {
+			diagonals[i % 8 + i / 8].hash |= 1 << i;
+			i += 1;
+		}
+		diagonals
+	};
+
+	/// The diagonals running from a8 towards h1, starting with the single square a1 and ending with h8
+	pub const ANTI_DIAGONALS: [BitBoard; 15] = {
+		let mut diagonals = [Self::EMPTY; 15];
+		let mut i = 0;
+		while i < 64 {
+			diagonals[7 + i % 8 - i / 8].hash |= 1 << i;
+			i += 1;
+		}
+		diagonals
+	};
+
+	pub const fn new() -> BitBoard {
 		BitBoard { hash: 0 }
 	}
 
-	pub fn contains(&self, v: u8) -> bool {
-		(self.hash & 1 << (v % 64)) != 0
+	pub fn square(v: Square) -> BitBoard {
+		BitBoard {
+			hash: 1 << v.index(),
+		}
 	}
 
-	pub fn insert(&mut self, v: u8) {
-		self.hash |= 1 << (v % 64)
+	/// The a1-h8 direction diagonal through `v`
+	pub fn diagonal(v: Square) -> BitBoard {
+		let v = v.index();
+		Self::DIAGONALS[(v % 8 + v / 8) as usize]
 	}
 
-	pub fn remove(&mut self, v: u8) {
+	/// The a8-h1 direction diagonal through `v`
+	pub fn anti_diagonal(v: Square) -> BitBoard {
+		let v = v.index();
+		Self::ANTI_DIAGONALS[(7 + v % 8 - v / 8) as usize]
+	}
+
+	/// All squares strictly between `a` and `b`, empty if they don't share a rank, file or diagonal
+	pub fn between(a: Square, b: Square) -> BitBoard {
+		BitBoard {
+			hash: BETWEEN[a.index() as usize][b.index() as usize],
+		}
+	}
+
+	/// The whole rank, file or diagonal through `a` and `b` from edge to edge, empty if they don't share one
+	pub fn line(a: Square, b: Square) -> BitBoard {
+		BitBoard {
+			hash: LINE[a.index() as usize][b.index() as usize],
+		}
+	}
+
+	pub fn north(self) -> BitBoard {
+		self >> 8
+	}
+
+	pub fn south(self) -> BitBoard {
+		self << 8
+	}
+
+	/// Shifts towards the h file. Squares on the h file fall off instead of wrapping onto the a file
+	pub fn east(self) -> BitBoard {
+		(self << 1) & !Self::FILES[0]
+	}
+
+	/// Shifts towards the a file. Squares on the a file fall off instead of wrapping onto the h file
+	pub fn west(self) -> BitBoard {
+		(self >> 1) & !Self::FILES[7]
+	}
+
+	pub fn north_east(self) -> BitBoard {
+		self.north().east()
+	}
+
+	pub fn north_west(self) -> BitBoard {
+		self.north().west()
+	}
+
+	pub fn south_east(self) -> BitBoard {
+		self.south().east()
+	}
+
+	pub fn south_west(self) -> BitBoard {
+		self.south().west()
+	}
+
+	/// The lowest square, which is the one closest to a8
+	pub fn lsb(&self) -> Option<Square> {
+		if self.hash == 0 {
+			None
+		} else {
+			Some(Square::new_unchecked(self.hash.trailing_zeros() as u8))
+		}
+	}
+
+	/// Removes and returns the lowest square
+	pub fn pop_lsb(&mut self) -> Option<Square> {
+		let lsb = self.lsb()?;
+		self.hash &= self.hash - 1;
+		Some(lsb)
+	}
+
+	pub fn contains(&self, v: Square) -> bool {
+		(self.hash & 1 << v.index()) != 0
+	}
+
+	pub fn insert(&mut self, v: Square) {
+		self.hash |= 1 << v.index()
+	}
+
+	pub fn remove(&mut self, v: Square) {
 		// Create a mask with a 1 at position 'v',
 		// then invert it to get a 0 at position 'v' and 1s everywhere else
-		self.hash &= !(1 << (v % 64));
+		self.hash &= !(1 << v.index());
 	}
 
 	pub fn iter(&self) -> BoardHashIter {
@@ -47,6 +185,13 @@ impl BitBoard {
 		}
 	}
 
+	/// Removes every square of `other`
+	pub fn difference(&self, other: Self) -> Self {
+		Self {
+			hash: self.hash & !other.hash,
+		}
+	}
+
 	pub fn size(&self) -> u8 {
 		self.hash.count_ones() as u8
 	}
@@ -57,7 +202,7 @@ pub struct BoardHashIter {
 }
 
 impl Iterator for BoardHashIter {
-	type Item = u8;
+	type Item = Square;
 
 	fn next(&mut self) -> Option<Self::Item> {
 		if self.remaining == 0 {
@@ -66,7 +211,7 @@ impl Iterator for BoardHashIter {
 
 		let tz = self.remaining.trailing_zeros(); // 0..=63
 		self.remaining &= !(1_u64 << tz);
-		Some(tz as u8)
+		Some(Square::new_unchecked(tz as u8))
 	}
 	fn size_hint(&self) -> (usize, Option<usize>) {
 		let cnt = self.remaining.count_ones() as usize;
@@ -77,7 +222,7 @@ impl Iterator for BoardHashIter {
 impl ExactSizeIterator for BoardHashIter {}
 
 impl IntoIterator for BitBoard {
-	type Item = u8;
+	type Item = Square;
 	type IntoIter = BoardHashIter;
 
 	fn into_iter(self) -> Self::IntoIter {
@@ -93,37 +238,258 @@ impl From<u64> for BitBoard {
 	}
 }
 
+impl From<BitBoard> for u64 {
+	fn from(value: BitBoard) -> Self {
+		value.hash
+	}
+}
+
+macro_rules! bit_op {
+	($op:ident, $fn:ident, $assign_op:ident, $assign_fn:ident) => {
+		impl $op for BitBoard {
+			type Output = BitBoard;
+
+			fn $fn(self, rhs: BitBoard) -> BitBoard {
+				BitBoard {
+					hash: self.hash.$fn(rhs.hash),
+				}
+			}
+		}
+
+		impl $assign_op for BitBoard {
+			fn $assign_fn(&mut self, rhs: BitBoard) {
+				self.hash.$assign_fn(rhs.hash);
+			}
+		}
+	};
+}
+
+bit_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+bit_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+bit_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl Not for BitBoard {
+	type Output = BitBoard;
+
+	fn not(self) -> BitBoard {
+		BitBoard { hash: !self.hash }
+	}
+}
+
+/// Plain shifts of the underlying bits, which wrap from one rank to the next. See [`BitBoard::east`] and friends for shifts that don't
+impl Shl<u8> for BitBoard {
+	type Output = BitBoard;
+
+	fn shl(self, rhs: u8) -> BitBoard {
+		BitBoard {
+			hash: self.hash << rhs,
+		}
+	}
+}
+
+impl ShlAssign<u8> for BitBoard {
+	fn shl_assign(&mut self, rhs: u8) {
+		self.hash <<= rhs;
+	}
+}
+
+impl Shr<u8> for BitBoard {
+	type Output = BitBoard;
+
+	fn shr(self, rhs: u8) -> BitBoard {
+		BitBoard {
+			hash: self.hash >> rhs,
+		}
+	}
+}
+
+impl ShrAssign<u8> for BitBoard {
+	fn shr_assign(&mut self, rhs: u8) {
+		self.hash >>= rhs;
+	}
+}
+
+/// The step from `a` towards `b` if both lie on a common rank, file or diagonal
+const fn direction(a: u8, b: u8) -> Option<(i8, i8)> {
+	let dx = (b % 8) as i8 - (a % 8) as i8;
+	let dy = (b / 8) as i8 - (a / 8) as i8;
+	if a == b || (dx != 0 && dy != 0 && dx.abs() != dy.abs()) {
+		None
+	} else {
+		Some((dx.signum(), dy.signum()))
+	}
+}
+
+/// Walks from `start` in steps of `(dx, dy)` until it leaves the board or reaches `stop`, collecting every square on the way but not `start` and `stop`
+const fn walk(start: u8, dx: i8, dy: i8, stop: u8) -> u64 {
+	let mut squares = 0;
+	let mut x = (start % 8) as i8 + dx;
+	let mut y = (start / 8) as i8 + dy;
+	while x >= 0 && x < 8 && y >= 0 && y < 8 {
+		let i = y as u8 * 8 + x as u8;
+		if i == stop {
+			break;
+		}
+		squares |= 1 << i;
+		x += dx;
+		y += dy;
+	}
+	squares
+}
+
+const fn square_table(line: bool) -> [[u64; 64]; 64] {
+	let mut table = [[0; 64]; 64];
+	let mut a = 0;
+	while a < 64 {
+		let mut b = 0;
+		while b < 64 {
+			if let Some((dx, dy)) = direction(a, b) {
+				table[a as usize][b as usize] = if line {
+					// 64 is never reached, so these walk until the edge
+					walk(a, dx, dy, 64) | walk(a, -dx, -dy, 64) | 1 << a
+				} else {
+					walk(a, dx, dy, b)
+				};
+			}
+			b += 1;
+		}
+		a += 1;
+	}
+	table
+}
+
+static BETWEEN: [[u64; 64]; 64] = square_table(false);
+static LINE: [[u64; 64]; 64] = square_table(true);
+
 impl Serialize for BitBoard {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: serde::Serializer,
 	{
-		let v: Vec<u8> = self.iter().collect();
+		let v: Vec<Square> = self.iter().collect();
 		v.serialize(serializer)
 	}
 }
 
 #[cfg(test)]
 mod tests {
-	use crate::bitboard::BitBoard;
+	use crate::{bitboard::BitBoard, square::Square};
 
 	#[test]
 	fn test_board_hash() {
 		let mut hasher = BitBoard::new();
 
-		for i in 0..62 {
-			hasher.insert(i);
-			assert_eq!(hasher.contains(i), true);
+		for sq in Square::all().take(62) {
+			hasher.insert(sq);
+			assert!(hasher.contains(sq));
 		}
 
-		assert_eq!(hasher.contains(63), false);
+		assert!(!hasher.contains(Square::H1));
 
-		hasher.remove(1);
+		hasher.remove(Square::B8);
 
-		assert_eq!(hasher.contains(1), false);
+		assert!(!hasher.contains(Square::B8));
 
-		let v: Vec<u8> = hasher.into_iter().collect();
+		let v: Vec<Square> = hasher.into_iter().collect();
 
 		assert_eq!(v.len(), 61)
 	}
+
+	#[test]
+	fn test_operators() {
+		let a = BitBoard::from(0b1100);
+		let b = BitBoard::from(0b1010);
+
+		assert_eq!(a & b, BitBoard::from(0b1000));
+		assert_eq!(a | b, BitBoard::from(0b1110));
+		assert_eq!(a ^ b, BitBoard::from(0b0110));
+		assert_eq!(!a & BitBoard::from(0b1111), BitBoard::from(0b0011));
+		assert_eq!(a << 1, BitBoard::from(0b11000));
+		assert_eq!(a >> 2, BitBoard::from(0b11));
+
+		let mut c = a;
+		c &= b;
+		c |= BitBoard::from(1);
+		c ^= BitBoard::from(0b1001);
+		assert!(c.is_empty());
+	}
+
+	#[test]
+	fn test_masks() {
+		assert!(BitBoard::FILES[0].contains(Square::A8) && BitBoard::FILES[0].contains(Square::A1));
+		assert!(BitBoard::FILES[7].contains(Square::H1));
+		assert!(BitBoard::RANKS[0].contains(Square::A1) && BitBoard::RANKS[0].contains(Square::H1));
+		assert!(BitBoard::RANKS[7].contains(Square::A8));
+
+		assert_eq!(
+			BitBoard::diagonal(Square::A1),
+			BitBoard::diagonal(Square::H8)
+		);
+		assert_eq!(BitBoard::diagonal(Square::A1).size(), 8);
+		assert_eq!(
+			BitBoard::anti_diagonal(Square::A8),
+			BitBoard::anti_diagonal(Square::H1)
+		);
+		assert_eq!(BitBoard::anti_diagonal(Square::A8).size(), 8);
+		assert_eq!(BitBoard::anti_diagonal(Square::H8).size(), 1);
+
+		let total = BitBoard::DIAGONALS
+			.iter()
+			.fold(BitBoard::EMPTY, |all, d| all | *d);
+		assert_eq!(total, BitBoard::FULL);
+	}
+
+	#[test]
+	fn test_shifts_dont_wrap() {
+		let h_file = BitBoard::FILES[7];
+		assert!(h_file.east().is_empty());
+		assert_eq!(h_file.west(), BitBoard::FILES[6]);
+		assert!(BitBoard::FILES[0].west().is_empty());
+
+		assert!(BitBoard::RANKS[7].north().is_empty());
+		assert_eq!(BitBoard::RANKS[0].north(), BitBoard::RANKS[1]);
+		assert!(BitBoard::RANKS[0].south().is_empty());
+
+		// nothing wraps around onto the a file
+		let h4 = BitBoard::square(Square::H4);
+		assert_eq!(h4.north_west(), BitBoard::square(Square::G5));
+		assert!(h4.north_east().is_empty());
+		assert!(h4.south_east().is_empty());
+		assert_eq!(h4.south_west(), BitBoard::square(Square::G3));
+	}
+
+	#[test]
+	fn test_lsb() {
+		let mut squares = BitBoard::from(0b1010_0000);
+		assert_eq!(squares.lsb(), Some(Square::F8));
+		assert_eq!(squares.pop_lsb(), Some(Square::F8));
+		assert_eq!(squares.pop_lsb(), Some(Square::H8));
+		assert_eq!(squares.pop_lsb(), None);
+		assert_eq!(squares.lsb(), None);
+	}
+
+	#[test]
+	fn test_between_and_line() {
+		let between: Vec<Square> = BitBoard::between(Square::A8, Square::D8)
+			.into_iter()
+			.collect();
+		assert_eq!(between, vec![Square::B8, Square::C8]);
+		assert_eq!(
+			BitBoard::between(Square::D8, Square::A8),
+			BitBoard::between(Square::A8, Square::D8)
+		);
+		assert_eq!(BitBoard::between(Square::A8, Square::H1).size(), 6);
+		assert!(BitBoard::between(Square::A8, Square::B8).is_empty());
+		// a8 and b6 aren't aligned
+		assert!(BitBoard::between(Square::A8, Square::B6).is_empty());
+
+		assert_eq!(BitBoard::line(Square::A8, Square::D8), BitBoard::RANKS[7]);
+		assert_eq!(
+			BitBoard::line(Square::B7, Square::C6),
+			BitBoard::anti_diagonal(Square::A8)
+		);
+		assert_eq!(BitBoard::line(Square::D4, Square::D8), BitBoard::FILES[3]);
+		assert!(BitBoard::line(Square::A8, Square::B6).is_empty());
+		assert!(BitBoard::line(Square::E8, Square::E8).is_empty());
+	}
 }
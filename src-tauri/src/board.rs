@@ -1,137 +1,161 @@
-use std::{fmt::Display, ops::Index};
+use std::ops::Index;
 
+use bitflags::bitflags;
 use serde::{Serialize, ser::SerializeMap};
 
 use crate::{
+	attacks,
 	bitboard::BitBoard,
+	game::{EndReason, Outcome},
+	moves::{Move, MoveError, MoveFlags},
+	notation::GameResult,
 	piece::{ChessCell, Color, Moved, PieceType},
+	square::{File, Rank, Square},
+	zobrist,
 };
 
-#[repr(C)]
-#[derive(Clone, Debug)]
-pub struct Board {
-	board: [ChessCell; 64],
-	occupation: BitBoard,
-}
-
-impl Default for Board {
-	fn default() -> Self {
-		Self::new()
+bitflags! {
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub struct CastlingRights: u8 {
+		const WHITE_KINGSIDE = 1;
+		const WHITE_QUEENSIDE = 1 << 1;
+		const BLACK_KINGSIDE = 1 << 2;
+		const BLACK_QUEENSIDE = 1 << 3;
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
-#[repr(transparent)]
-struct BoardIndex {
-	i: u8,
-}
+impl CastlingRights {
+	pub fn for_color(col: Color) -> Self {
+		match col {
+			Color::White => Self::WHITE_KINGSIDE | Self::WHITE_QUEENSIDE,
+			Color::Black => Self::BLACK_KINGSIDE | Self::BLACK_QUEENSIDE,
+		}
+	}
 
-impl BoardIndex {
-	pub fn new(u: u8) -> Self {
-		BoardIndex { i: u }
+	pub fn kingside(col: Color) -> Self {
+		match col {
+			Color::White => Self::WHITE_KINGSIDE,
+			Color::Black => Self::BLACK_KINGSIDE,
+		}
 	}
 
-	pub fn none() -> Self {
-		BoardIndex { i: 255 }
+	pub fn queenside(col: Color) -> Self {
+		match col {
+			Color::White => Self::WHITE_QUEENSIDE,
+			Color::Black => Self::BLACK_QUEENSIDE,
+		}
 	}
 
-	pub fn get(&self) -> Option<u8> {
-		if self.i > 63 { None } else { Some(self.i) }
+	/// The right that is lost once anything moves from or to the rook's home square `i`
+	fn for_rook_square(i: Square) -> Self {
+		match i {
+			Square::H1 => Self::WHITE_KINGSIDE,
+			Square::A1 => Self::WHITE_QUEENSIDE,
+			Square::H8 => Self::BLACK_KINGSIDE,
+			Square::A8 => Self::BLACK_QUEENSIDE,
+			_ => Self::empty(),
+		}
 	}
 }
 
-impl Display for BoardIndex {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{:?}", self.get())
+impl Default for CastlingRights {
+	fn default() -> Self {
+		Self::empty()
 	}
 }
 
-impl Board {
-	fn board_overflow_add(start: u8, b: u8, diag: bool) -> BoardIndex {
-		let res = start + b;
-		if res > 63 {
-			return BoardIndex::none();
-		}
-
-		let res_col = res % 8;
-		match diag {
-			true => {
-				if b > 8 {
-					if res_col < start % 8 {
-						return BoardIndex::none();
-					}
-				} else if res_col > start % 8 {
-					return BoardIndex::none();
-				}
-			}
-			false => {
-				// horizontal
-				if res_col < start % 8 {
-					return BoardIndex::none();
-				}
-			}
-		}
+/// Everything [`Board::make_move`] overwrites, so [`Board::unmake_move`] can restore it
+#[derive(Clone, Copy, Debug)]
+pub struct Undo {
+	mv: Move,
+	piece: ChessCell,
+	captured: ChessCell,
+	/// The rook as it was before castling
+	rook: ChessCell,
+	castling: CastlingRights,
+	en_passant: Option<Square>,
+	halfmove_clock: u16,
+	fullmove_number: u16,
+}
 
-		BoardIndex::new(res)
+impl Undo {
+	pub fn get_move(&self) -> Move {
+		self.mv
 	}
+}
 
-	fn board_underflow_sub(start: u8, b: u8, diag: bool) -> BoardIndex {
-		let res = match start.checked_sub(b) {
-			None => return BoardIndex::none(),
-			Some(r) => r,
-		};
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+	board: [ChessCell; 64],
+	occupation: BitBoard,
+	/// The squares of every piece type, indexed by [`piece_index`] and kept in sync with `board`
+	pieces: [BitBoard; 6],
+	/// The squares of each color's pieces, indexed by [`color_index`]
+	colors: [BitBoard; 2],
+	side_to_move: Color,
+	castling: CastlingRights,
+	/// The square a pawn skipped over with its double step in the previous move
+	en_passant: Option<Square>,
+	/// Halfmoves since the last capture or pawn move, used for the fifty-move rule
+	halfmove_clock: u16,
+	/// Starts at 1 and is incremented after every move of black
+	fullmove_number: u16,
+	/// The Zobrist hash of the position, updated along with every change to it
+	zobrist: u64,
+}
 
-		let res_col = res % 8;
-		match diag {
-			true => {
-				if b > 8 {
-					if res_col > start % 8 {
-						return BoardIndex::none();
-					}
-				} else if res_col < start % 8 {
-					return BoardIndex::none();
-				}
-			}
-			false => {
-				// horizontal
-				if res_col > start % 8 {
-					return BoardIndex::none();
-				}
-			}
-		}
-		BoardIndex::new(res)
+impl Default for Board {
+	fn default() -> Self {
+		Self::new()
 	}
+}
 
-	fn coords_to_index(x: u8, y: u8) -> Option<u8> {
-		if x < 8 && y < 8 {
-			let y = (y as i8 - 7).unsigned_abs();
-			Some(y * 8 + x)
-		} else {
-			None
-		}
-	}
+/// The light squares, a8 being one of them
+const LIGHT_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
 
-	pub fn notation_to_x(x: char) -> Option<u8> {
-		let x = (x as u8).wrapping_sub(97);
-		if x < 8 { Some(x) } else { None }
-	}
+fn piece_index(pt: PieceType) -> usize {
+	pt as usize - 1
+}
 
-	pub fn notation_to_index(x: char, y: u8) -> Option<u8> {
-		// convert 'a' into 0. We don't care if any overflow or anything else occurs here. If its anything but 'a'-'h' it should just return a none
-		let x = (x as u8).wrapping_sub(97);
-		Self::coords_to_index(x, y - 1)
+fn color_index(col: Color) -> usize {
+	match col {
+		Color::White => 0,
+		Color::Black => 1,
 	}
+}
+
+/// Everything a pawn can be promoted into
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+	PieceType::Queen,
+	PieceType::Rook,
+	PieceType::Bishop,
+	PieceType::Knight,
+];
 
+impl Board {
 	pub fn empty() -> Self {
 		Board {
 			board: [ChessCell::default(); 64],
 			occupation: BitBoard::new(),
+			pieces: [BitBoard::new(); 6],
+			colors: [BitBoard::new(); 2],
+			side_to_move: Color::White,
+			castling: CastlingRights::empty(),
+			en_passant: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			// an empty board with white to move, no castling rights and no en passant square hashes to 0
+			zobrist: 0,
 		}
 	}
 
-	pub fn add_piece(&mut self, piece: ChessCell, position: u8) {
-		self.board[position as usize] = piece;
-		self.occupation.insert(position);
+	pub fn add_piece(&mut self, piece: ChessCell, position: Square) {
+		self.set_cell(position, piece);
+	}
+
+	pub fn remove_piece(&mut self, position: Square) {
+		self.set_cell(position, ChessCell::empty());
 	}
 
 	pub fn new() -> Self {
@@ -148,10 +172,10 @@ impl Board {
 			board[61] = w_bishop;
 
 			let w_queen = ChessCell::with_piece(PieceType::Queen, Color::White, Moved::No);
-			board[60] = w_queen;
+			board[59] = w_queen;
 
 			let w_king = ChessCell::with_piece(PieceType::King, Color::White, Moved::No);
-			board[59] = w_king;
+			board[60] = w_king;
 
 			board[58] = w_bishop;
 			board[57] = w_knight;
@@ -200,376 +224,690 @@ impl Board {
 			board[15] = w_pawn;
 		}
 
-		Self {
-			board,
-			occupation: BitBoard::from(
-				// this is the inital position of all pieces on the board
-				0b1111111111111111000000000000000000000000000000001111111111111111,
-			),
+		let mut start = Self::empty();
+		for (i, cell) in Square::all().zip(board) {
+			start.set_cell(i, cell);
 		}
+		start.set_castling_rights(CastlingRights::all());
+		start
 	}
 
 	pub fn reset(&mut self) {
-		let n = Self::new();
-		self.board = n.board;
-		self.occupation = n.occupation;
+		*self = Self::new();
 	}
 
-	pub fn get_at_position(&self, x: u8, y: u8) -> ChessCell {
-		let index = match Self::coords_to_index(x, y) {
-			Some(i) => i,
-			None => return ChessCell::empty(),
-		};
-		self[index]
+	pub fn get_side_to_move(&self) -> Color {
+		self.side_to_move
 	}
 
-	pub fn move_piece_unchecked(&mut self, index: u8, target: u8) {
-		let mut piece = self[index];
-		if !piece.is_empty() {
-			piece.set_moved(true);
-			self.board[index as usize] = ChessCell::empty();
-			self.board[target as usize] = piece;
-			self.occupation.remove(index);
-			self.occupation.insert(target);
-		}
+	pub fn set_side_to_move(&mut self, col: Color) {
+		self.zobrist ^= zobrist::side_key(self.side_to_move) ^ zobrist::side_key(col);
+		self.side_to_move = col;
 	}
 
-	fn get_pawn_moves(&self, i: u8) -> BitBoard {
-		let mut legal = BitBoard::new();
+	pub fn get_castling_rights(&self) -> CastlingRights {
+		self.castling
+	}
 
-		let piece = self[i];
-		let own_col = piece.get_color();
+	pub fn set_castling_rights(&mut self, rights: CastlingRights) {
+		self.zobrist ^= zobrist::castling_key(self.castling) ^ zobrist::castling_key(rights);
+		self.castling = rights;
+	}
 
-		let col = piece.get_color();
-		let math = match col {
-			Color::Black => {
-				if !piece.has_moved() {
-					legal.insert(i + 16); // no need to do a checked add because we haven't moved so we can't overflow
-				}
-				Self::board_overflow_add
-			}
+	/// The Zobrist hash of the position, covering the pieces, side to move, castling rights and en passant file but not the move counters
+	pub fn hash(&self) -> u64 {
+		self.zobrist
+	}
 
-			Color::White => {
-				if !piece.has_moved() {
-					legal.insert(i - 16); // no need to do a checked sub because we haven't moved so we can't underflow
-				}
-				Self::board_underflow_sub
-			}
-		};
+	pub fn get_en_passant(&self) -> Option<Square> {
+		self.en_passant
+	}
 
-		if let Some(step) = math(i, 8, false).get()
-			&& !self.occupation.contains(step)
-		{
-			legal.insert(step);
+	/// Sets the square skipped by a pawn's double step and marks that pawn as capturable en passant
+	pub fn set_en_passant(&mut self, target: Option<Square>) {
+		if let Some(previous) = self.en_passant {
+			self.mark_en_passant(Self::en_passant_victim(previous), false);
 		}
-
-		if let Some(left_take) = math(i, 7, true).get()
-			&& self.occupation.contains(left_take)
-			&& own_col != self[left_take].get_color()
-		{
-			legal.insert(left_take);
+		if let Some(target) = target {
+			self.mark_en_passant(Self::en_passant_victim(target), true);
 		}
+		self.zobrist ^= zobrist::en_passant_key(self.en_passant) ^ zobrist::en_passant_key(target);
+		self.en_passant = target;
+	}
 
-		if let Some(right_take) = math(i, 9, true).get()
-			&& self.occupation.contains(right_take)
-			&& own_col != self[right_take].get_color()
-		{
-			legal.insert(right_take);
+	fn mark_en_passant(&mut self, i: Square, value: bool) {
+		let cell = &mut self.board[i.index() as usize];
+		if cell.get_type() == Some(PieceType::Pawn) {
+			cell.set_en_pessant(value);
 		}
+	}
 
-		legal
+	pub fn get_halfmove_clock(&self) -> u16 {
+		self.halfmove_clock
 	}
 
-	fn get_knight_moves(&self, i: u8) -> BitBoard {
-		let mut legal = BitBoard::new();
+	pub fn set_halfmove_clock(&mut self, clock: u16) {
+		self.halfmove_clock = clock;
+	}
 
-		// TODO: fix it being very borked
+	pub fn get_fullmove_number(&self) -> u16 {
+		self.fullmove_number
+	}
 
-		if let Some(top_left) = Self::board_underflow_sub(i, 17, false).get() {
-			legal.insert(top_left);
-		}
+	pub fn set_fullmove_number(&mut self, number: u16) {
+		self.fullmove_number = number;
+	}
 
-		if let Some(top_right) = Self::board_underflow_sub(i, 15, false).get() {
-			legal.insert(top_right);
+	pub fn get_at_position(&self, file: File, rank: Rank) -> ChessCell {
+		self[Square::from_coords(file, rank)]
+	}
+
+	/// Moves the piece on `index` to `target` without checking whether that is legal, and updates the game state accordingly.
+	/// A pawn reaching the last rank becomes `promotion`, or a queen if none was chosen
+	pub fn move_piece_unchecked(
+		&mut self,
+		index: Square,
+		target: Square,
+		promotion: Option<PieceType>,
+	) {
+		if self[index].is_empty() {
+			return;
 		}
 
-		if let Some(bottom_right) = Self::board_overflow_add(i, 17, false).get() {
-			legal.insert(bottom_right);
+		let promotion = if self.is_promotion(index, target) {
+			Some(promotion.unwrap_or(PieceType::Queen))
+		} else {
+			None
+		};
+		self.make_move(self.create_move(index, target, promotion));
+	}
+
+	/// Builds the move of the piece on `index` to `target` after checking it is legal in the current position.
+	/// `promotion` defaults to a queen, like in [`Board::move_piece_unchecked`]
+	pub fn validate_move(
+		&self,
+		index: u8,
+		target: u8,
+		promotion: Option<PieceType>,
+	) -> Result<Move, MoveError> {
+		let from = Square::new(index).ok_or(MoveError::OutOfRange(index))?;
+		let to = Square::new(target).ok_or(MoveError::OutOfRange(target))?;
+
+		let piece = self[from];
+		if piece.is_empty() {
+			return Err(MoveError::NoPiece(from));
+		}
+		if piece.get_color() != self.side_to_move {
+			return Err(MoveError::NotYourTurn(from));
 		}
 
-		if let Some(bottom_left) = Self::board_overflow_add(i, 15, false).get() {
-			legal.insert(bottom_left);
+		let promotion = if self.is_promotion(from, to) {
+			let promotion = promotion.unwrap_or(PieceType::Queen);
+			if !PROMOTION_PIECES.contains(&promotion) {
+				return Err(MoveError::IllegalMove { from, to });
+			}
+			Some(promotion)
+		} else {
+			None
+		};
+
+		if !self.get_legal_moves(from).contains(to) {
+			return Err(MoveError::IllegalMove { from, to });
 		}
+		Ok(self.create_move(from, to, promotion))
+	}
 
-		if let Some(high_left) = Self::board_underflow_sub(i, 10, false).get() {
-			legal.insert(high_left);
+	/// Whether moving the piece on `from` to `to` is a pawn reaching the last rank
+	pub fn is_promotion(&self, from: Square, to: Square) -> bool {
+		self[from].get_type() == Some(PieceType::Pawn)
+			&& matches!(to.rank(), Rank::One | Rank::Eight)
+	}
+
+	/// Builds the move of the piece on `from` to `to`, deriving its flags from the current position
+	pub fn create_move(&self, from: Square, to: Square, promotion: Option<PieceType>) -> Move {
+		let piece = self[from];
+		let mut flags = MoveFlags::empty();
+
+		if self.occupation.contains(to) {
+			flags.insert(MoveFlags::CAPTURE);
 		}
 
-		if let Some(high_right) = Self::board_underflow_sub(i, 6, false).get() {
-			legal.insert(high_right);
+		match piece.get_type() {
+			Some(PieceType::Pawn) => {
+				if from.rank().index().abs_diff(to.rank().index()) == 2 {
+					flags.insert(MoveFlags::DOUBLE_PUSH);
+				} else if self.en_passant == Some(to) && from.file() != to.file() {
+					flags.insert(MoveFlags::CAPTURE | MoveFlags::EN_PASSANT);
+				}
+			}
+			Some(PieceType::King)
+				if from.rank() == to.rank()
+					&& from.file().index().abs_diff(to.file().index()) == 2 =>
+			{
+				flags.insert(MoveFlags::CASTLE);
+			}
+			_ => {}
 		}
 
-		if let Some(low_left) = Self::board_overflow_add(i, 6, false).get() {
-			legal.insert(low_left);
+		Move::new(from, to, promotion, flags)
+	}
+
+	/// The rook's origin and target when the king castles to `king_target`
+	fn castling_rook(king_target: Square) -> (Square, Square) {
+		let rank = king_target.rank();
+		match king_target.file() {
+			File::G => (
+				Square::from_coords(File::H, rank),
+				Square::from_coords(File::F, rank),
+			),
+			_ => (
+				Square::from_coords(File::A, rank),
+				Square::from_coords(File::D, rank),
+			),
 		}
+	}
 
-		if let Some(low_right) = Self::board_overflow_add(i, 10, false).get() {
-			legal.insert(low_right);
+	/// The square of the pawn that skipped over `target`, which is also the one captured by an en passant capture to `target`
+	fn en_passant_victim(target: Square) -> Square {
+		// the skipped square is on the 6th rank if black made the double step, and on the 3rd if white did
+		let rank = match target.rank() {
+			Rank::Six => Rank::Five,
+			_ => Rank::Four,
+		};
+		Square::from_coords(target.file(), rank)
+	}
+
+	/// Every write to the mailbox goes through here, so the bitboards stay in sync with it
+	fn set_cell(&mut self, i: Square, cell: ChessCell) {
+		let previous = self.board[i.index() as usize];
+		self.zobrist ^= zobrist::piece_key(previous, i) ^ zobrist::piece_key(cell, i);
+		if let Some(pt) = previous.get_type() {
+			self.pieces[piece_index(pt)].remove(i);
+			self.colors[color_index(previous.get_color())].remove(i);
+			self.occupation.remove(i);
 		}
 
-		legal
+		self.board[i.index() as usize] = cell;
+		if let Some(pt) = cell.get_type() {
+			self.pieces[piece_index(pt)].insert(i);
+			self.colors[color_index(cell.get_color())].insert(i);
+			self.occupation.insert(i);
+		}
 	}
 
-	fn get_bishop_moves(&self, i: u8) -> BitBoard {
-		let mut legal = BitBoard::new();
-		let piece = self[i];
-		let own_color = piece.get_color();
+	/// Plays `mv` without checking whether it is legal and updates the game state accordingly.
+	/// The returned [`Undo`] can be passed to [`Board::unmake_move`] to take the move back
+	pub fn make_move(&mut self, mv: Move) -> Undo {
+		let from = mv.get_from();
+		let to = mv.get_to();
+		let piece = self[from];
+		let col = piece.get_color();
 
-		let mut top_right_chk = i;
-		while let Some(top_right) = Self::board_underflow_sub(top_right_chk, 7, true).get() {
-			if self.occupation.contains(top_right) {
-				if self[top_right].get_color() != own_color {
-					legal.insert(top_right);
-				}
-				break;
-			}
+		let captured_square = if mv.is_en_passant() {
+			Self::en_passant_victim(to)
+		} else {
+			to
+		};
 
-			legal.insert(top_right);
-			top_right_chk = top_right;
+		// the right to capture en passant expires after one ply
+		let previous_en_passant = self.en_passant;
+		self.set_en_passant(None);
+
+		let mut undo = Undo {
+			mv,
+			piece,
+			captured: self[captured_square],
+			rook: ChessCell::empty(),
+			castling: self.castling,
+			en_passant: previous_en_passant,
+			halfmove_clock: self.halfmove_clock,
+			fullmove_number: self.fullmove_number,
+		};
+
+		let mut moved = piece;
+		moved.set_moved(true);
+		if let Some(promotion) = mv.get_promotion() {
+			moved.set_type(promotion);
 		}
 
-		let mut top_left_chk = i;
-		while let Some(top_left) = Self::board_underflow_sub(top_left_chk, 9, true).get() {
-			if self.occupation.contains(top_left) {
-				if self[top_left].get_color() != own_color {
-					legal.insert(top_left);
-				}
-				break;
-			}
+		self.set_cell(captured_square, ChessCell::empty());
+		self.set_cell(from, ChessCell::empty());
+		self.set_cell(to, moved);
+
+		if mv.is_castle() {
+			let (rook_from, rook_to) = Self::castling_rook(to);
+			undo.rook = self[rook_from];
 
-			legal.insert(top_left);
-			top_left_chk = top_left;
+			let mut rook = undo.rook;
+			rook.set_moved(true);
+			self.set_cell(rook_from, ChessCell::empty());
+			self.set_cell(rook_to, rook);
 		}
 
-		let mut bottom_left_chk = i;
-		while let Some(bottom_left) = Self::board_overflow_add(bottom_left_chk, 7, true).get() {
-			if self.occupation.contains(bottom_left) {
-				if self[bottom_left].get_color() != own_color {
-					legal.insert(bottom_left);
-				}
-				break;
-			}
+		let mut castling = self.castling;
+		if piece.get_type() == Some(PieceType::King) {
+			castling.remove(CastlingRights::for_color(col));
+		}
+		// a rook leaving its home square or being captured on it loses the right as well
+		castling.remove(CastlingRights::for_rook_square(from));
+		castling.remove(CastlingRights::for_rook_square(to));
+		self.set_castling_rights(castling);
 
-			legal.insert(bottom_left);
-			bottom_left_chk = bottom_left;
+		if mv.get_flags().contains(MoveFlags::DOUBLE_PUSH) {
+			self.set_en_passant(Some(Square::new_unchecked((from.index() + to.index()) / 2)));
 		}
 
-		let mut bottom_right_chk = i;
-		while let Some(bottom_right) = Self::board_overflow_add(bottom_right_chk, 9, true).get() {
-			if self.occupation.contains(bottom_right) {
-				if self[bottom_right].get_color() != own_color {
-					legal.insert(bottom_right);
-				}
-				break;
-			}
+		if piece.get_type() == Some(PieceType::Pawn) || mv.is_capture() {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+		}
 
-			legal.insert(bottom_right);
-			bottom_right_chk = bottom_right;
+		if col == Color::Black {
+			self.fullmove_number = self.fullmove_number.saturating_add(1);
 		}
+		self.set_side_to_move(col.opposite());
 
-		legal
+		undo
 	}
 
-	fn get_rook_moves(&self, i: u8) -> BitBoard {
-		let mut legal = BitBoard::new();
-		let piece = self[i];
-		let own_color = piece.get_color();
-
-		// top
-		let mut top_chk = i;
-		while let Some(top) = Self::board_underflow_sub(top_chk, 8, false).get() {
-			if self.occupation.contains(top) {
-				if self[top].get_color() != own_color {
-					legal.insert(top);
-				}
-				break;
-			}
-
-			legal.insert(top);
-			top_chk = top;
+	/// Takes back the move `undo` was created for, restoring the position exactly as it was before
+	pub fn unmake_move(&mut self, undo: Undo) {
+		let mv = undo.mv;
+		let from = mv.get_from();
+		let to = mv.get_to();
+		let col = undo.piece.get_color();
+
+		if mv.is_castle() {
+			let (rook_from, rook_to) = Self::castling_rook(to);
+			self.set_cell(rook_to, ChessCell::empty());
+			self.set_cell(rook_from, undo.rook);
 		}
 
-		// bottom
-		let mut bottom_chk = i;
-		while let Some(bottom) = Self::board_overflow_add(bottom_chk, 8, false).get() {
-			if self.occupation.contains(bottom) {
-				if self[bottom].get_color() != own_color {
-					legal.insert(bottom);
-				}
-				break;
-			}
+		self.set_cell(to, ChessCell::empty());
+		self.set_cell(from, undo.piece);
 
-			legal.insert(bottom);
-			bottom_chk = bottom;
-		}
+		let captured_square = if mv.is_en_passant() {
+			Self::en_passant_victim(to)
+		} else {
+			to
+		};
+		self.set_cell(captured_square, undo.captured);
+
+		self.set_en_passant(undo.en_passant);
+		self.set_castling_rights(undo.castling);
+		self.halfmove_clock = undo.halfmove_clock;
+		self.fullmove_number = undo.fullmove_number;
+		self.set_side_to_move(col);
+	}
 
-		let mut right_chk = i;
-		while let Some(right) = Self::board_overflow_add(right_chk, 1, false).get() {
-			if self.occupation.contains(right) {
-				if self[right].get_color() != own_color {
-					legal.insert(right);
+	/// Every legal move of the side to move. A pawn reaching the last rank results in one move per piece it can become
+	pub fn get_all_legal_moves(&self) -> Vec<Move> {
+		let mut moves = Vec::new();
+		for from in self.color_occupancy(self.side_to_move) {
+			for to in self.get_legal_moves(from) {
+				if self.is_promotion(from, to) {
+					for promotion in PROMOTION_PIECES {
+						moves.push(self.create_move(from, to, Some(promotion)));
+					}
+				} else {
+					moves.push(self.create_move(from, to, None));
 				}
-				break;
 			}
-
-			legal.insert(right);
-			right_chk = right;
 		}
+		moves
+	}
 
-		let mut left_chk = i;
-		while let Some(left) = Self::board_underflow_sub(left_chk, 1, false).get() {
-			if self.occupation.contains(left) {
-				if self[left].get_color() != own_color {
-					legal.insert(left);
-				}
-				break;
-			}
+	/// Every occupied square
+	pub fn occupancy(&self) -> BitBoard {
+		self.occupation
+	}
 
-			legal.insert(left);
-			left_chk = left;
-		}
+	/// Every square of `col`'s pieces
+	pub fn color_occupancy(&self, col: Color) -> BitBoard {
+		self.colors[color_index(col)]
+	}
 
-		legal
+	/// Every square of a piece of type `pt`, no matter the color
+	pub fn piece_type_occupancy(&self, pt: PieceType) -> BitBoard {
+		self.pieces[piece_index(pt)]
 	}
 
-	fn get_queen_moves(&self, i: u8) -> BitBoard {
-		let bishop = self.get_bishop_moves(i);
-		bishop.union(self.get_rook_moves(i))
+	/// The squares of `col`'s pieces of type `pt`
+	pub fn pieces(&self, col: Color, pt: PieceType) -> BitBoard {
+		self.piece_type_occupancy(pt)
+			.join(self.color_occupancy(col))
 	}
 
-	fn get_king_moves(&self, i: u8) -> BitBoard {
-		let mut legal = BitBoard::new();
+	fn king_position(&self, col: Color) -> Option<Square> {
+		self.pieces(col, PieceType::King).iter().next()
+	}
 
+	/// The squares the piece on `i` attacks, with sliders being blocked by `occupation`
+	fn piece_attacks(&self, i: Square, occupation: BitBoard) -> BitBoard {
 		let piece = self[i];
-		let own_col = piece.get_color();
+		match piece.get_type() {
+			Some(PieceType::Pawn) => attacks::pawn_attacks(i, piece.get_color()),
+			Some(PieceType::Knight) => attacks::knight_attacks(i),
+			Some(PieceType::Bishop) => attacks::bishop_attacks(i, occupation),
+			Some(PieceType::Rook) => attacks::rook_attacks(i, occupation),
+			Some(PieceType::Queen) => attacks::queen_attacks(i, occupation),
+			Some(PieceType::King) => attacks::king_attacks(i),
+			None => BitBoard::new(),
+		}
+	}
 
-		if let Some(bottom_left) = Self::board_overflow_add(i, 7, true).get()
-			&& (!self.occupation.contains(bottom_left) || self[bottom_left].get_color() != own_col)
-		{
-			legal.insert(bottom_left);
-		};
+	/// Every square attacked by at least one piece of `col`
+	fn attacked_by(&self, col: Color, occupation: BitBoard) -> BitBoard {
+		let mut attacked = BitBoard::new();
+		for i in self.color_occupancy(col) {
+			attacked = attacked.union(self.piece_attacks(i, occupation));
+		}
+		attacked
+	}
 
-		if let Some(bottom) = Self::board_overflow_add(i, 8, false).get()
-			&& (!self.occupation.contains(bottom) || self[bottom].get_color() != own_col)
-		{
-			legal.insert(bottom);
-		};
+	/// The pieces of `col` that attack `square`, with sliders being blocked by `occupation`
+	fn attackers(&self, square: Square, col: Color, occupation: BitBoard) -> BitBoard {
+		// every attack is symmetric, apart from pawns, so look from the square back at the pieces that could reach it
+		let queens = self.pieces(col, PieceType::Queen);
+		let diagonal = self.pieces(col, PieceType::Bishop).union(queens);
+		let straight = self.pieces(col, PieceType::Rook).union(queens);
+
+		attacks::pawn_attacks(square, col.opposite())
+			.join(self.pieces(col, PieceType::Pawn))
+			.union(attacks::knight_attacks(square).join(self.pieces(col, PieceType::Knight)))
+			.union(attacks::bishop_attacks(square, occupation).join(diagonal))
+			.union(attacks::rook_attacks(square, occupation).join(straight))
+			.union(attacks::king_attacks(square).join(self.pieces(col, PieceType::King)))
+	}
 
-		if let Some(bottom_right) = Self::board_overflow_add(i, 9, true).get()
-			&& (!self.occupation.contains(bottom_right)
-				|| self[bottom_right].get_color() != own_col)
-		{
-			legal.insert(bottom_right);
-		};
+	/// The pieces of the opposite color that currently attack `col`'s king on `king`
+	fn checkers(&self, col: Color, king: Square) -> BitBoard {
+		self.attackers(king, col.opposite(), self.occupation)
+	}
 
-		if let Some(left) = Self::board_underflow_sub(i, 1, false).get()
-			&& (!self.occupation.contains(left) || self[left].get_color() != own_col)
-		{
-			legal.insert(left);
-		};
+	pub fn is_in_check(&self, col: Color) -> bool {
+		match self.king_position(col) {
+			Some(king) => !self.checkers(col, king).is_empty(),
+			None => false,
+		}
+	}
 
-		if let Some(right) = Self::board_overflow_add(i, 1, false).get()
-			&& (!self.occupation.contains(right) || self[right].get_color() != own_col)
-		{
-			legal.insert(right);
+	/// If the piece on `i` is absolutely pinned to the king on `king`, returns the line it may still move along (including the pinning piece).
+	/// Returns a full board otherwise
+	fn pin_mask(&self, i: Square, king: Square) -> BitBoard {
+		let unpinned = BitBoard::from(u64::MAX);
+		if BitBoard::line(king, i).is_empty() {
+			return unpinned;
+		}
+
+		let diagonal = king.file() != i.file() && king.rank() != i.rank();
+		let slider_attacks = if diagonal {
+			attacks::bishop_attacks
+		} else {
+			attacks::rook_attacks
 		};
 
-		if let Some(top_left) = Self::board_underflow_sub(i, 9, true).get()
-			&& (!self.occupation.contains(top_left) || self[top_left].get_color() != own_col)
-		{
-			legal.insert(top_left);
+		// taking the piece away reveals the next piece behind it, but only if it was the first one on the king's line
+		let mut occupation = self.occupation;
+		occupation.remove(i);
+		let revealed = slider_attacks(king, occupation)
+			.difference(slider_attacks(king, self.occupation))
+			.join(occupation);
+		let Some(pinner) = revealed.iter().next() else {
+			return unpinned;
 		};
 
-		if let Some(top) = Self::board_underflow_sub(i, 8, false).get()
-			&& (!self.occupation.contains(top) || self[top].get_color() != own_col)
-		{
-			legal.insert(top);
+		let slides = match self[pinner].get_type() {
+			Some(PieceType::Queen) => true,
+			Some(PieceType::Bishop) => diagonal,
+			Some(PieceType::Rook) => !diagonal,
+			_ => false,
 		};
 
-		if let Some(top_right) = Self::board_underflow_sub(i, 7, true).get()
-			&& (!self.occupation.contains(top_right) || self[top_right].get_color() != own_col)
-		{
-			legal.insert(top_right);
+		if self[pinner].get_color() != self[i].get_color() && slides {
+			BitBoard::between(king, pinner) | BitBoard::square(pinner)
+		} else {
+			unpinned
+		}
+	}
+
+	fn get_pawn_moves(&self, i: Square, en_passant: Option<Square>) -> BitBoard {
+		let piece = self[i];
+		let own_col = piece.get_color();
+
+		// a pawn that just made a single step from its start can continue for the double step
+		let (push, skipped_rank): (fn(BitBoard) -> BitBoard, _) = match own_col {
+			Color::White => (BitBoard::north, BitBoard::RANKS[2]),
+			Color::Black => (BitBoard::south, BitBoard::RANKS[5]),
 		};
 
-		let king = self[i];
-		if !king.has_moved() {
-			// TODO: test
-
-			let kingside_i: u8;
-			let queenside_i: u8;
-			let kingside_mask: BitBoard;
-			let queenside_mask: BitBoard;
-
-			if own_col == Color::Black {
-				kingside_i = 0;
-				queenside_i = 7;
-				kingside_mask = BitBoard::from(
-					0b0000000000000000000000000000000000000000000000000110000000000000,
-				);
-				queenside_mask = BitBoard::from(
-					0b0000000000000000000000000000000000000000000000000000000000001110,
-				);
-			} else {
-				// white
-				kingside_i = 56;
-				queenside_i = 63;
-				kingside_mask = BitBoard::from(
-					0b0110000000000000000000000000000000000000000000000000000000000000,
-				);
-				queenside_mask = BitBoard::from(
-					0b0000000000001110000000000000000000000000000000000000000000000000,
-				);
-			}
+		let empty = !self.occupation;
+		let single_step = push(BitBoard::square(i)) & empty;
+		let double_step = push(single_step & skipped_rank) & empty;
 
-			let kingside = self[kingside_i];
-			if kingside.get_color() == own_col
-				&& !kingside.has_moved()
-				&& kingside_mask.join(self.occupation).is_empty()
-			{
-				legal.insert(kingside_i);
-			}
+		let mut enemies = self.color_occupancy(own_col.opposite());
+		if let Some(en_passant) = en_passant {
+			enemies.insert(en_passant);
+		}
+		single_step | double_step | (attacks::pawn_attacks(i, own_col) & enemies)
+	}
 
-			let queenside = self[queenside_i];
-			if queenside.get_color() == own_col
-				&& !queenside.has_moved()
-				&& queenside_mask.join(self.occupation).is_empty()
-			{
-				legal.insert(queenside_i);
+	/// An en passant capture removes two pieces from the same rank, which can expose the king in ways a pin doesn't cover.
+	/// So it is simply played out to see whether the king ends up in check
+	fn is_en_passant_legal(&self, i: Square, target: Square) -> bool {
+		let mut board = self.clone();
+		board.make_move(self.create_move(i, target, None));
+		!board.is_in_check(self[i].get_color())
+	}
+
+	fn get_king_moves(&self, i: Square) -> BitBoard {
+		let piece = self[i];
+		let own_col = piece.get_color();
+
+		// the king itself must not block a slider, otherwise it could step back along the checking line
+		let mut occupation = self.occupation;
+		occupation.remove(i);
+		let danger = self.attacked_by(own_col.opposite(), occupation);
+
+		let mut legal = attacks::king_attacks(i)
+			.difference(self.color_occupancy(own_col))
+			.difference(danger);
+
+		let (home, kingside_rook, queenside_rook) = match own_col {
+			Color::White => (Square::E1, Square::H1, Square::A1),
+			Color::Black => (Square::E8, Square::H8, Square::A8),
+		};
+
+		// castling is neither allowed out of check, nor through or into an attacked square
+		if i == home && !danger.contains(i) {
+			for (right, rook) in [
+				(CastlingRights::kingside(own_col), kingside_rook),
+				(CastlingRights::queenside(own_col), queenside_rook),
+			] {
+				let target = if rook > i {
+					Square::new_unchecked(i.index() + 2)
+				} else {
+					Square::new_unchecked(i.index() - 2)
+				};
+				let king_path = BitBoard::between(i, target) | BitBoard::square(target);
+
+				if self.castling.contains(right)
+					&& self[rook].get_type() == Some(PieceType::Rook)
+					&& self[rook].get_color() == own_col
+					&& (BitBoard::between(i, rook) & self.occupation).is_empty()
+					&& king_path.join(danger).is_empty()
+				{
+					legal.insert(target);
+				}
 			}
 		}
 
 		legal
 	}
 
-	pub fn get_legal_moves(&self, i: u8) -> BitBoard {
+	/// Moves that follow the movement rules of the piece on `i`, without considering whether they leave the own king in check
+	fn get_pseudo_legal_moves(&self, i: Square, en_passant: Option<Square>) -> BitBoard {
 		let c = self[i];
 		match c.get_type() {
-			Some(PieceType::Pawn) => self.get_pawn_moves(i),
-			Some(PieceType::Knight) => self.get_knight_moves(i),
-			Some(PieceType::Bishop) => self.get_bishop_moves(i),
-			Some(PieceType::Rook) => self.get_rook_moves(i),
-			Some(PieceType::Queen) => self.get_queen_moves(i),
+			Some(PieceType::Pawn) => self.get_pawn_moves(i, en_passant),
 			Some(PieceType::King) => self.get_king_moves(i),
-			_ => BitBoard::new(),
+			Some(_) => self
+				.piece_attacks(i, self.occupation)
+				.difference(self.color_occupancy(c.get_color())),
+			None => BitBoard::new(),
 		}
 	}
+
+	/// The en passant square, but only if a pawn can actually capture there
+	fn capturable_en_passant(&self) -> Option<Square> {
+		let target = self.en_passant?;
+		self.color_occupancy(self.side_to_move)
+			.into_iter()
+			.any(|i| {
+				self[i].get_type() == Some(PieceType::Pawn)
+					&& self.get_legal_moves(i).contains(target)
+			})
+			.then_some(target)
+	}
+
+	/// Whether both boards count as the same position for the repetition rules.
+	/// That means the same pieces on the same squares, the same side to move and the same possible moves, so move counters and moved pieces are ignored
+	pub fn is_same_position(&self, other: &Board) -> bool {
+		self.pieces == other.pieces
+			&& self.colors == other.colors
+			&& self.side_to_move == other.side_to_move
+			&& self.castling == other.castling
+			&& self.capturable_en_passant() == other.capturable_en_passant()
+	}
+
+	/// Whether neither side could ever deliver mate, no matter how badly the other one plays
+	pub fn has_insufficient_material(&self) -> bool {
+		let majors_or_pawns = [PieceType::Pawn, PieceType::Rook, PieceType::Queen]
+			.into_iter()
+			.any(|pt| !self.piece_type_occupancy(pt).is_empty());
+		if majors_or_pawns {
+			return false;
+		}
+
+		let knights = self.piece_type_occupancy(PieceType::Knight);
+		let bishops = self.piece_type_occupancy(PieceType::Bishop);
+		let light = BitBoard::from(LIGHT_SQUARES);
+
+		// a single minor piece can't mate, and neither can any number of bishops that all stay on the same square color
+		knights.size() + bishops.size() <= 1
+			|| (knights.is_empty()
+				&& (bishops.join(light).is_empty() || bishops.difference(light).is_empty()))
+	}
+
+	/// Whether and how the game has ended, as far as this position alone can tell.
+	/// Repetitions need the game's history, see [`crate::game::Game::status`]
+	pub fn status(&self) -> Option<Outcome> {
+		if !self.has_legal_moves() {
+			if self.is_in_check(self.side_to_move) {
+				let result = match self.side_to_move {
+					Color::White => GameResult::Black,
+					Color::Black => GameResult::White,
+				};
+				return Some(Outcome {
+					result,
+					reason: EndReason::Checkmate,
+				});
+			}
+			return Some(Outcome::draw(EndReason::Stalemate));
+		}
+
+		if self.halfmove_clock >= 150 {
+			Some(Outcome::draw(EndReason::SeventyFiveMoveRule))
+		} else if self.has_insufficient_material() {
+			Some(Outcome::draw(EndReason::InsufficientMaterial))
+		} else if self.halfmove_clock >= 100 {
+			Some(Outcome::draw(EndReason::FiftyMoveRule))
+		} else {
+			None
+		}
+	}
+
+	/// Whether the side to move has at least one legal move
+	pub fn has_legal_moves(&self) -> bool {
+		self.color_occupancy(self.side_to_move)
+			.into_iter()
+			.any(|i| !self.get_legal_moves(i).is_empty())
+	}
+
+	/// Returns every square the piece on `i` can legally move to.
+	/// Moves that would leave the own king in check, like stepping into an attacked square, leaving an absolute pin or ignoring a check, are excluded.
+	/// Only pieces of the side to move have legal moves
+	pub fn get_legal_moves(&self, i: Square) -> BitBoard {
+		let piece = self[i];
+		if piece.is_empty() || piece.get_color() != self.side_to_move {
+			return BitBoard::new();
+		}
+		self.legal_moves(i, self.en_passant)
+	}
+
+	/// Returns every square the piece on `i` could legally move to if it was its side's turn.
+	/// For the side not to move, en passant is left out, since the en passant square belongs to the other side
+	pub fn get_mobility(&self, i: Square) -> BitBoard {
+		let piece = self[i];
+		if piece.is_empty() {
+			BitBoard::new()
+		} else if piece.get_color() == self.side_to_move {
+			self.legal_moves(i, self.en_passant)
+		} else {
+			self.legal_moves(i, None)
+		}
+	}
+
+	/// The legal moves of the piece on `i`, no matter whose turn it is, with `en_passant` as the square it may capture en passant on
+	fn legal_moves(&self, i: Square, en_passant: Option<Square>) -> BitBoard {
+		let piece = self[i];
+		let pseudo_legal = self.get_pseudo_legal_moves(i, en_passant);
+
+		// the king already filters out attacked squares itself
+		if piece.get_type() == Some(PieceType::King) {
+			return pseudo_legal;
+		}
+
+		// without a king there is nothing that could be left in check
+		let Some(king) = self.king_position(piece.get_color()) else {
+			return pseudo_legal;
+		};
+
+		let checkers = self.checkers(piece.get_color(), king);
+		let check_mask = match checkers.size() {
+			0 => BitBoard::from(u64::MAX),
+			1 => {
+				// the check can be resolved by capturing the checker or by blocking its line
+				let checker = checkers.iter().next().unwrap();
+				BitBoard::between(king, checker) | BitBoard::square(checker)
+			}
+			// in a double check only the king may move
+			_ => return BitBoard::new(),
+		};
+
+		let mut legal = pseudo_legal.join(check_mask).join(self.pin_mask(i, king));
+
+		if piece.get_type() == Some(PieceType::Pawn)
+			&& let Some(en_passant) = en_passant
+			&& pseudo_legal.contains(en_passant)
+		{
+			// the checker might be the pawn that is captured, which isn't on the target square
+			if self.is_en_passant_legal(i, en_passant) {
+				legal.insert(en_passant);
+			} else {
+				legal.remove(en_passant);
+			}
+		}
+
+		legal
+	}
 }
 
-impl Index<u8> for Board {
+impl Index<Square> for Board {
 	type Output = ChessCell;
-	fn index(&self, index: u8) -> &Self::Output {
-		&self.board[index as usize]
+	fn index(&self, index: Square) -> &Self::Output {
+		&self.board[index.index() as usize]
 	}
 }
 
@@ -587,3 +925,410 @@ impl Serialize for Board {
 		state.end()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		bitboard::BitBoard,
+		board::{Board, CastlingRights, PROMOTION_PIECES},
+		moves::{MoveError, MoveFlags},
+		piece::{ChessCell, Color, Moved, PieceType},
+		square::Square,
+	};
+
+	fn place(board: &mut Board, pt: PieceType, col: Color, notation: &str) -> Square {
+		let index = notation.parse().unwrap();
+		board.add_piece(ChessCell::with_piece(pt, col, Moved::Yes), index);
+		index
+	}
+
+	fn squares(notations: &[&str]) -> BitBoard {
+		let mut bb = BitBoard::new();
+		for n in notations {
+			bb.insert(n.parse().unwrap());
+		}
+		bb
+	}
+
+	/// Only sliders blocking each other, without kings
+	fn sliders_position() -> Board {
+		let mut board = Board::empty();
+		place(&mut board, PieceType::Rook, Color::Black, "e4");
+		place(&mut board, PieceType::Bishop, Color::Black, "d4");
+		place(&mut board, PieceType::Bishop, Color::White, "a8");
+		place(&mut board, PieceType::Bishop, Color::White, "h8");
+		place(&mut board, PieceType::Bishop, Color::Black, "h1");
+		place(&mut board, PieceType::Bishop, Color::Black, "a1");
+		board.set_side_to_move(Color::Black);
+		board
+	}
+
+	#[test]
+	fn test_sliders_block_each_other() {
+		let board = sliders_position();
+		assert_eq!(
+			board.get_legal_moves(index("e4")),
+			squares(&["e8", "e7", "e6", "e5", "e3", "e2", "e1", "f4", "g4", "h4"])
+		);
+		assert_eq!(
+			board.get_legal_moves(index("d4")),
+			squares(&[
+				"a7", "b6", "c5", "e5", "f6", "g7", "h8", "c3", "b2", "e3", "f2", "g1"
+			])
+		);
+		assert_eq!(board.get_legal_moves(index("h1")), squares(&["g2", "f3"]));
+	}
+
+	#[test]
+	fn test_knight_does_not_wrap() {
+		let mut board = Board::empty();
+		let knight = place(&mut board, PieceType::Knight, Color::White, "a1");
+		assert_eq!(board.get_legal_moves(knight), squares(&["b3", "c2"]));
+
+		let knight = place(&mut board, PieceType::Knight, Color::White, "h5");
+		assert_eq!(
+			board.get_legal_moves(knight),
+			squares(&["g7", "f6", "f4", "g3"])
+		);
+	}
+
+	#[test]
+	fn test_mobility() {
+		// the pawn on d2 attacks the en passant square, which only black could capture on
+		let board = Board::from_fen("4k3/8/8/8/4P3/8/3P4/4K3 b - e3 0 1").unwrap();
+		assert!(board.get_legal_moves(index("d2")).is_empty());
+		assert_eq!(board.get_mobility(index("d2")), squares(&["d3", "d4"]));
+		assert_eq!(
+			board.get_mobility(index("e8")),
+			board.get_legal_moves(index("e8"))
+		);
+		assert!(board.get_mobility(index("a1")).is_empty());
+	}
+
+	#[test]
+	fn test_pinned_piece() {
+		let mut board = Board::empty();
+		place(&mut board, PieceType::King, Color::White, "e1");
+		let rook = place(&mut board, PieceType::Rook, Color::White, "e4");
+		let bishop = place(&mut board, PieceType::Bishop, Color::White, "d2");
+		place(&mut board, PieceType::Rook, Color::Black, "e8");
+		place(&mut board, PieceType::Bishop, Color::Black, "a5");
+		place(&mut board, PieceType::King, Color::Black, "h8");
+
+		assert_eq!(
+			board.get_legal_moves(rook),
+			squares(&["e2", "e3", "e5", "e6", "e7", "e8"])
+		);
+		assert_eq!(board.get_legal_moves(bishop), squares(&["c3", "b4", "a5"]));
+	}
+
+	#[test]
+	fn test_check_and_king_safety() {
+		let mut board = Board::empty();
+		let king = place(&mut board, PieceType::King, Color::White, "e1");
+		let knight = place(&mut board, PieceType::Knight, Color::White, "g1");
+		let pawn = place(&mut board, PieceType::Pawn, Color::White, "h2");
+		place(&mut board, PieceType::Rook, Color::Black, "a1");
+		place(&mut board, PieceType::King, Color::Black, "e8");
+
+		assert!(board.is_in_check(Color::White));
+		// neither the knight nor the pawn can block or capture the rook
+		assert_eq!(board.get_legal_moves(knight), BitBoard::new());
+		assert_eq!(board.get_legal_moves(pawn), BitBoard::new());
+		// the king can't step back along the rook's line
+		assert_eq!(board.get_legal_moves(king), squares(&["d2", "e2", "f2"]));
+	}
+
+	#[test]
+	fn test_double_check() {
+		let mut board = Board::empty();
+		let king = place(&mut board, PieceType::King, Color::White, "e1");
+		let queen = place(&mut board, PieceType::Queen, Color::White, "d4");
+		place(&mut board, PieceType::Rook, Color::Black, "e6");
+		place(&mut board, PieceType::Knight, Color::Black, "d3");
+		place(&mut board, PieceType::King, Color::Black, "a8");
+
+		// the queen could capture either checker, but that doesn't resolve a double check
+		assert_eq!(board.get_legal_moves(queen), BitBoard::new());
+		assert_eq!(board.get_legal_moves(king), squares(&["d1", "f1", "d2"]));
+	}
+
+	#[test]
+	fn test_game_state_updates() {
+		let mut board = Board::new();
+		let black_pawn = Square::E7;
+		assert_eq!(board.get_legal_moves(black_pawn), BitBoard::new());
+
+		let from = Square::E2;
+		let to = Square::E4;
+		board.move_piece_unchecked(from, to, None);
+		assert_eq!(board.get_side_to_move(), Color::Black);
+		assert_eq!(board.get_en_passant(), Some(Square::E3));
+		assert_eq!(board.get_fullmove_number(), 1);
+
+		let from = Square::G8;
+		let to = Square::F6;
+		board.move_piece_unchecked(from, to, None);
+		assert_eq!(board.get_side_to_move(), Color::White);
+		assert_eq!(board.get_en_passant(), None);
+		assert_eq!(board.get_halfmove_clock(), 1);
+		assert_eq!(board.get_fullmove_number(), 2);
+	}
+
+	#[test]
+	fn test_castling_right_lost_by_capture() {
+		let mut board = Board::empty();
+		place(&mut board, PieceType::King, Color::White, "e1");
+		place(&mut board, PieceType::Rook, Color::White, "h1");
+		place(&mut board, PieceType::King, Color::Black, "e8");
+		let bishop = place(&mut board, PieceType::Bishop, Color::Black, "c6");
+		board.set_castling_rights(CastlingRights::WHITE_KINGSIDE);
+		board.set_side_to_move(Color::Black);
+
+		board.move_piece_unchecked(bishop, Square::H1, None);
+		assert_eq!(board.get_castling_rights(), CastlingRights::empty());
+		assert_eq!(board.get_halfmove_clock(), 0);
+	}
+
+	fn index(notation: &str) -> Square {
+		notation.parse().unwrap()
+	}
+
+	#[test]
+	fn test_make_unmake_restores_position() {
+		let positions = [
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 3 7",
+		];
+
+		for fen in positions {
+			let board = Board::from_fen(fen).unwrap();
+			let moves = board.get_all_legal_moves();
+			assert!(!moves.is_empty());
+
+			for mv in moves {
+				let mut played = board.clone();
+				let undo = played.make_move(mv);
+				assert_ne!(played, board);
+				played.unmake_move(undo);
+				assert_eq!(played, board);
+			}
+		}
+	}
+
+	#[test]
+	fn test_piece_bitboards() {
+		let board = Board::new();
+		assert_eq!(board.occupancy().size(), 32);
+		assert_eq!(board.color_occupancy(Color::White).size(), 16);
+		assert_eq!(board.pieces(Color::White, PieceType::Pawn).size(), 8);
+		assert_eq!(
+			board.pieces(Color::Black, PieceType::King),
+			BitBoard::square(Square::E8)
+		);
+
+		// the bitboards have to follow the mailbox through castling, en passant and promotions
+		let board = Board::from_fen("r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6 0 1").unwrap();
+		for mv in board.get_all_legal_moves() {
+			let mut played = board.clone();
+			played.make_move(mv);
+
+			for col in [Color::White, Color::Black] {
+				for pt in PROMOTION_PIECES
+					.into_iter()
+					.chain([PieceType::Pawn, PieceType::King])
+				{
+					let expected = Square::all()
+						.filter(|i| {
+							played[*i].get_type() == Some(pt) && played[*i].get_color() == col
+						})
+						.fold(BitBoard::new(), |mut squares, i| {
+							squares.insert(i);
+							squares
+						});
+					assert_eq!(played.pieces(col, pt), expected, "{mv}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_make_special_moves() {
+		let board = Board::from_fen("r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6 0 1").unwrap();
+
+		let castle = board.create_move(index("e1"), index("g1"), None);
+		assert_eq!(castle.get_flags(), MoveFlags::CASTLE);
+		let mut played = board.clone();
+		let undo = played.make_move(castle);
+		assert_eq!(played.to_fen(), "r3k3/1P6/8/3pP3/8/8/8/5RK1 b q - 1 1");
+		played.unmake_move(undo);
+		assert_eq!(played, board);
+
+		let en_passant = board.create_move(index("e5"), index("d6"), None);
+		assert_eq!(
+			en_passant.get_flags(),
+			MoveFlags::CAPTURE | MoveFlags::EN_PASSANT
+		);
+		let mut played = board.clone();
+		let undo = played.make_move(en_passant);
+		assert_eq!(played.to_fen(), "r3k3/1P6/3P4/8/8/8/8/4K2R b Kq - 0 1");
+		played.unmake_move(undo);
+		assert_eq!(played, board);
+
+		let promotion = board.create_move(index("b7"), index("a8"), Some(PieceType::Knight));
+		let mut played = board.clone();
+		let undo = played.make_move(promotion);
+		assert_eq!(played.to_fen(), "N3k3/8/8/3pP3/8/8/8/4K2R b K - 0 1");
+		played.unmake_move(undo);
+		assert_eq!(played, board);
+	}
+
+	#[test]
+	fn test_en_passant() {
+		let mut board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+		board.make_move(board.create_move(index("d7"), index("d5"), None));
+		assert_eq!(board.get_en_passant(), Some(index("d6")));
+		assert!(board[index("d5")].eligable_en_pessant_take());
+		assert_eq!(board.get_legal_moves(index("e5")), squares(&["d6", "e6"]));
+
+		// the right expires after one ply
+		board.make_move(board.create_move(index("e1"), index("d1"), None));
+		board.make_move(board.create_move(index("e8"), index("d8"), None));
+		assert_eq!(board.get_en_passant(), None);
+		assert!(!board[index("d5")].eligable_en_pessant_take());
+		assert_eq!(board.get_legal_moves(index("e5")), squares(&["e6"]));
+	}
+
+	#[test]
+	fn test_en_passant_legality() {
+		// both pawns leave the rank, which would expose the king to the rook
+		let board = Board::from_fen("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").unwrap();
+		assert_eq!(board.get_legal_moves(index("e5")), squares(&["e6"]));
+
+		// capturing the pawn that gives check resolves it, even though it isn't on the target square
+		let board = Board::from_fen("8/8/8/3pP3/4K3/8/8/7k w - d6 0 1").unwrap();
+		assert!(board.is_in_check(Color::White));
+		assert_eq!(board.get_legal_moves(index("e5")), squares(&["d6"]));
+	}
+
+	#[test]
+	fn test_promotion() {
+		let board = Board::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+		let promotions: Vec<_> = board
+			.get_all_legal_moves()
+			.into_iter()
+			.filter(|mv| mv.get_from() == index("a7"))
+			.collect();
+		assert_eq!(promotions.len(), 8);
+		for piece in [
+			PieceType::Queen,
+			PieceType::Rook,
+			PieceType::Bishop,
+			PieceType::Knight,
+		] {
+			assert!(
+				promotions
+					.iter()
+					.any(|mv| mv.get_to() == index("b8") && mv.get_promotion() == Some(piece))
+			);
+		}
+
+		let mut played = board.clone();
+		played.move_piece_unchecked(index("a7"), index("b8"), Some(PieceType::Knight));
+		assert_eq!(played.to_fen(), "1N2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+		let mut played = board.clone();
+		played.move_piece_unchecked(index("a7"), index("a8"), None);
+		assert_eq!(played.to_fen(), "Qr2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+	}
+
+	#[test]
+	fn test_start_position() {
+		assert_eq!(Board::new().to_fen(), crate::fen::START_FEN);
+	}
+
+	#[test]
+	fn test_castling() {
+		let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+		assert_eq!(
+			board.get_legal_moves(index("e1")),
+			squares(&["c1", "d1", "f1", "g1", "d2", "e2", "f2"])
+		);
+
+		let mut played = board.clone();
+		played.move_piece_unchecked(index("e1"), index("c1"), None);
+		assert_eq!(played.to_fen(), "r3k2r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+		played.move_piece_unchecked(index("e8"), index("g8"), None);
+		assert_eq!(played.to_fen(), "r4rk1/8/8/8/8/8/8/2KR3R w - - 2 2");
+
+		// f1 is attacked, b1 only has to be empty
+		let board = Board::from_fen("r3k3/8/8/8/8/8/6p1/R3K2R w KQq - 0 1").unwrap();
+		assert_eq!(
+			board.get_legal_moves(index("e1")),
+			squares(&["c1", "d1", "d2", "e2", "f2"])
+		);
+		let board = Board::from_fen("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+		assert!(board.get_legal_moves(index("e1")).contains(index("c1")));
+
+		// not out of check
+		let board = Board::from_fen("4k3/8/8/8/8/3n4/8/R3K2R w KQ - 0 1").unwrap();
+		assert!(board.is_in_check(Color::White));
+		assert!(!board.get_legal_moves(index("e1")).contains(index("g1")));
+		assert!(!board.get_legal_moves(index("e1")).contains(index("c1")));
+
+		// and not with a piece in between
+		let board = Board::from_fen("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+		assert!(board.get_legal_moves(index("e1")).contains(index("g1")));
+		assert!(!board.get_legal_moves(index("e1")).contains(index("c1")));
+	}
+
+	#[test]
+	fn test_validate_move() {
+		let board = Board::new();
+		let e2 = Square::E2.index();
+		let e4 = Square::E4.index();
+
+		let mv = board.validate_move(e2, e4, None).unwrap();
+		assert_eq!(mv.get_flags(), MoveFlags::DOUBLE_PUSH);
+
+		assert_eq!(
+			board.validate_move(64, e4, None),
+			Err(MoveError::OutOfRange(64))
+		);
+		assert_eq!(
+			board.validate_move(e2, 200, None),
+			Err(MoveError::OutOfRange(200))
+		);
+		assert_eq!(
+			board.validate_move(Square::E4.index(), Square::E5.index(), None),
+			Err(MoveError::NoPiece(Square::E4))
+		);
+		assert_eq!(
+			board.validate_move(Square::E7.index(), Square::E5.index(), None),
+			Err(MoveError::NotYourTurn(Square::E7))
+		);
+		assert_eq!(
+			board.validate_move(e2, Square::E5.index(), None),
+			Err(MoveError::IllegalMove {
+				from: Square::E2,
+				to: Square::E5
+			})
+		);
+
+		let board = Board::from_fen("8/P6k/8/8/8/8/6K1/8 w - - 0 1").unwrap();
+		let (a7, a8) = (Square::A7.index(), Square::A8.index());
+		assert_eq!(
+			board.validate_move(a7, a8, None).unwrap().get_promotion(),
+			Some(PieceType::Queen)
+		);
+		assert_eq!(
+			board
+				.validate_move(a7, a8, Some(PieceType::Knight))
+				.unwrap()
+				.get_promotion(),
+			Some(PieceType::Knight)
+		);
+		assert!(board.validate_move(a7, a8, Some(PieceType::King)).is_err());
+	}
+}
@@ -0,0 +1,191 @@
+use std::{
+	sync::{Arc, atomic::AtomicBool},
+	time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	engine::{Engine, SearchLimits, SearchResult},
+	eval::Weights,
+	game::Game,
+	moves::Move,
+	piece::Color,
+};
+
+/// How well the computer plays. Its search stops at whichever limit is reached first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Strength {
+	pub depth: Option<u32>,
+	pub nodes: Option<u64>,
+	/// In milliseconds
+	pub time: Option<u64>,
+	/// How many centipawns worse than the best move the chosen one may be, see [`Engine::set_randomness`]
+	pub randomness: i32,
+}
+
+impl Default for Strength {
+	fn default() -> Self {
+		Self {
+			depth: None,
+			nodes: None,
+			time: Some(1000),
+			randomness: 0,
+		}
+	}
+}
+
+impl Strength {
+	/// Without any limit the computer thinks for as long as it does by default
+	pub fn limits(&self) -> SearchLimits {
+		let limits = SearchLimits {
+			depth: self.depth,
+			nodes: self.nodes,
+			time: self.time.map(Duration::from_millis),
+		};
+		if limits == SearchLimits::default() {
+			Strength::default().limits()
+		} else {
+			limits
+		}
+	}
+}
+
+/// The engine playing one side of a game
+pub struct Computer {
+	color: Color,
+	strength: Strength,
+	engine: Engine,
+}
+
+impl Computer {
+	pub fn new(color: Color, strength: Strength) -> Self {
+		let mut engine = Engine::new();
+		engine.set_randomness(strength.randomness);
+		Self {
+			color,
+			strength,
+			engine,
+		}
+	}
+
+	pub fn get_color(&self) -> Color {
+		self.color
+	}
+
+	pub fn get_strength(&self) -> Strength {
+		self.strength
+	}
+
+	pub fn set_weights(&mut self, weights: Weights) {
+		self.engine.set_weights(weights);
+	}
+
+	/// Setting the returned flag makes a running [`Computer::think`] return as soon as possible.
+	/// Like [`Engine::stop_handle`], it has to be cleared before thinking again
+	pub fn stop_handle(&self) -> Arc<AtomicBool> {
+		self.engine.stop_handle()
+	}
+
+	/// Whether the computer has to move next in `game`. It waits while moves are taken back, playing would discard
+	/// the moves after the cursor
+	pub fn is_turn(&self, game: &Game) -> bool {
+		game.get_board().get_side_to_move() == self.color
+			&& game.get_cursor() == game.get_moves().len()
+			&& !game.is_over()
+	}
+
+	/// Searches the position of `game` for the computer's move, calling `on_iteration` with every completed iteration.
+	/// Returns `None` if there is no legal move
+	pub fn think(&mut self, game: &Game, on_iteration: impl FnMut(&SearchResult)) -> Option<Move> {
+		self.engine.set_previous_positions(game.position_hashes());
+		let mut board = game.get_board().clone();
+		self.engine
+			.search_with(&mut board, self.strength.limits(), on_iteration)
+			.get_best_move()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use crate::{
+		board::Board,
+		computer::{Computer, Strength},
+		engine::SearchLimits,
+		game::Game,
+		piece::Color,
+		square::Square,
+	};
+
+	#[test]
+	fn test_strength_limits() {
+		let strength = Strength {
+			depth: Some(3),
+			..Strength::default()
+		};
+		assert_eq!(
+			strength.limits(),
+			SearchLimits {
+				depth: Some(3),
+				nodes: None,
+				time: Some(Duration::from_secs(1)),
+			}
+		);
+
+		let unlimited = Strength {
+			time: None,
+			..Strength::default()
+		};
+		assert_eq!(unlimited.limits(), Strength::default().limits());
+
+		let parsed: Strength =
+			serde_json::from_str(r#"{"nodes": 1000, "randomness": 20}"#).unwrap();
+		assert_eq!(parsed.nodes, Some(1000));
+		assert_eq!(parsed.randomness, 20);
+		assert_eq!(parsed.time, Some(1000));
+	}
+
+	#[test]
+	fn test_think() {
+		let strength = Strength {
+			depth: Some(2),
+			..Strength::default()
+		};
+		let mut computer = Computer::new(Color::Black, strength);
+
+		let mut game = Game::default();
+		assert!(!computer.is_turn(&game));
+		game.move_piece(Square::E2.index(), Square::E4.index(), None)
+			.unwrap();
+		assert!(computer.is_turn(&game));
+
+		let mut iterations = 0;
+		let mv = computer.think(&game, |_| iterations += 1).unwrap();
+		assert_eq!(iterations, 2);
+		assert!(game.get_board().get_all_legal_moves().contains(&mv));
+
+		// taking back moves doesn't make the computer reply, only going back to the last one does
+		game.play(mv);
+		game.move_piece(Square::G1.index(), Square::F3.index(), None)
+			.unwrap();
+		assert!(computer.is_turn(&game));
+		// back to before the computer's reply
+		assert!(game.undo());
+		assert!(game.undo());
+		assert!(!computer.is_turn(&game));
+		game.goto_ply(1);
+		assert!(!computer.is_turn(&game));
+		game.goto_ply(3);
+		assert!(computer.is_turn(&game));
+
+		// checkmated, so nothing to do
+		let mated =
+			Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+				.unwrap();
+		let computer = Computer::new(Color::White, strength);
+		assert!(!computer.is_turn(&Game::new(mated)));
+	}
+}
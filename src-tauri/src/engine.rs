@@ -0,0 +1,738 @@
+use std::{
+	cmp::Reverse,
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+	board::Board,
+	eval::Weights,
+	moves::Move,
+	piece::{Color, PieceType},
+	zobrist::next_random,
+};
+
+/// The score of being checkmated right now. Mates further away score `MATE` minus the plies until the mate,
+/// so shorter mates are preferred
+pub const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+/// The deepest the search goes, including check extensions
+pub const MAX_PLY: usize = 64;
+/// Any score beyond this is a mate within [`MAX_PLY`]
+const MATE_THRESHOLD: i32 = MATE - MAX_PLY as i32;
+
+const DEFAULT_HASH_SIZE_MB: usize = 16;
+/// How many nodes are searched between checks of the time limit and the stop flag
+const CHECK_INTERVAL: u64 = 2048;
+
+/// When to stop searching. The search ends at whichever limit is reached first, or at [`MAX_PLY`] if none is set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+	pub depth: Option<u32>,
+	pub nodes: Option<u64>,
+	pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+	pub fn depth(depth: u32) -> Self {
+		Self {
+			depth: Some(depth),
+			..Self::default()
+		}
+	}
+
+	pub fn time(time: Duration) -> Self {
+		Self {
+			time: Some(time),
+			..Self::default()
+		}
+	}
+}
+
+/// The outcome of the deepest fully searched iteration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchResult {
+	best_move: Option<Move>,
+	/// In centipawns, from the point of view of the side to move. `None` if not even depth 1 was completed
+	score: Option<i32>,
+	/// 0 if not even depth 1 was completed
+	depth: u32,
+	pv: Vec<Move>,
+	nodes: u64,
+}
+
+impl SearchResult {
+	/// `None` if the side to move has no legal moves
+	pub fn get_best_move(&self) -> Option<Move> {
+		self.best_move
+	}
+
+	pub fn get_score(&self) -> Option<i32> {
+		self.score
+	}
+
+	pub fn get_depth(&self) -> u32 {
+		self.depth
+	}
+
+	/// The principal variation, the line both sides are expected to play, starting with the best move
+	pub fn get_pv(&self) -> &[Move] {
+		&self.pv
+	}
+
+	pub fn get_nodes(&self) -> u64 {
+		self.nodes
+	}
+
+	/// In how many moves the side to move mates, negative if it gets mated. `None` if the score isn't a mate
+	pub fn get_mate(&self) -> Option<i32> {
+		let score = self.score?;
+		if score > MATE_THRESHOLD {
+			Some((MATE - score + 1) / 2)
+		} else if score < -MATE_THRESHOLD {
+			Some(-(MATE + score) / 2)
+		} else {
+			None
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+	Exact,
+	/// The search failed high, the real score is at least this
+	Lower,
+	/// The search failed low, the real score is at most this
+	Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TtEntry {
+	key: u64,
+	best_move: Option<Move>,
+	score: i32,
+	depth: u32,
+	bound: Bound,
+}
+
+/// Remembers the results of positions that were already searched, indexed by [`Board::hash`]
+struct TranspositionTable {
+	entries: Vec<Option<TtEntry>>,
+}
+
+impl TranspositionTable {
+	fn new(size_mb: usize) -> Self {
+		let len = (size_mb * 1024 * 1024 / size_of::<Option<TtEntry>>()).max(1);
+		Self {
+			entries: vec![None; len],
+		}
+	}
+
+	fn index(&self, key: u64) -> usize {
+		(key % self.entries.len() as u64) as usize
+	}
+
+	fn probe(&self, key: u64) -> Option<TtEntry> {
+		self.entries[self.index(key)].filter(|e| e.key == key)
+	}
+
+	/// Entries of other positions are only replaced by searches that went at least as deep
+	fn store(&mut self, entry: TtEntry) {
+		let i = self.index(entry.key);
+		let replace = match self.entries[i] {
+			Some(old) => old.key == entry.key || old.depth <= entry.depth,
+			None => true,
+		};
+		if replace {
+			self.entries[i] = Some(entry);
+		}
+	}
+
+	fn clear(&mut self) {
+		self.entries.fill(None);
+	}
+}
+
+/// Mate scores are stored relative to the node instead of the root, so they stay correct when reached through another path
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+	if score > MATE_THRESHOLD {
+		score + ply as i32
+	} else if score < -MATE_THRESHOLD {
+		score - ply as i32
+	} else {
+		score
+	}
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+	if score > MATE_THRESHOLD {
+		score - ply as i32
+	} else if score < -MATE_THRESHOLD {
+		score + ply as i32
+	} else {
+		score
+	}
+}
+
+fn color_index(col: Color) -> usize {
+	match col {
+		Color::White => 0,
+		Color::Black => 1,
+	}
+}
+
+/// An iterative deepening alpha-beta search. The transposition table and move ordering statistics are kept between searches
+pub struct Engine {
+	tt: TranspositionTable,
+	weights: Weights,
+	/// Quiet moves that caused a beta cutoff, two per ply
+	killers: [[Option<Move>; 2]; MAX_PLY + 1],
+	/// How often a quiet move caused a cutoff, weighted by depth and indexed by color, origin and target
+	history: Box<[[[i32; 64]; 64]; 2]>,
+	/// The principal variation found from every ply
+	pv: Vec<Vec<Move>>,
+	/// Hashes of the positions leading to the current node, for repetition detection
+	path: Vec<u64>,
+	/// Hashes of the positions played before the searched one, oldest first
+	previous_positions: Vec<u64>,
+	nodes: u64,
+	limits: SearchLimits,
+	start: Instant,
+	stop: Arc<AtomicBool>,
+	stopped: bool,
+	/// The most centipawns a root move's score can be raised by at random, see [`Engine::set_randomness`]
+	randomness: i32,
+	rng: u64,
+	/// Drawn from `rng` for every search, so a root move keeps its bonus across iterations
+	seed: u64,
+}
+
+impl Default for Engine {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Engine {
+	pub fn new() -> Self {
+		Self::with_hash_size(DEFAULT_HASH_SIZE_MB)
+	}
+
+	/// An engine whose transposition table takes up about `size_mb` megabytes
+	pub fn with_hash_size(size_mb: usize) -> Self {
+		Self {
+			tt: TranspositionTable::new(size_mb),
+			weights: Weights::default(),
+			killers: [[None; 2]; MAX_PLY + 1],
+			history: Box::new([[[0; 64]; 64]; 2]),
+			pv: vec![Vec::new(); MAX_PLY + 1],
+			path: Vec::new(),
+			previous_positions: Vec::new(),
+			nodes: 0,
+			limits: SearchLimits::default(),
+			start: Instant::now(),
+			stop: Arc::new(AtomicBool::new(false)),
+			stopped: false,
+			randomness: 0,
+			rng: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map_or(0, |t| t.as_nanos() as u64),
+			seed: 0,
+		}
+	}
+
+	pub fn set_hash_size(&mut self, size_mb: usize) {
+		self.tt = TranspositionTable::new(size_mb);
+	}
+
+	pub fn set_weights(&mut self, weights: Weights) {
+		self.weights = weights;
+	}
+
+	/// The [`Board::hash`]es of the positions of the game so far, so the search can avoid or aim for repetitions
+	pub fn set_previous_positions(&mut self, positions: Vec<u64>) {
+		self.previous_positions = positions;
+	}
+
+	/// Lets the search pick root moves scoring up to `randomness` centipawns less than the best one, for weaker and
+	/// less predictable play. Mates are always played. With any randomness every root move is searched with a full
+	/// window, which makes the search slower
+	pub fn set_randomness(&mut self, randomness: i32) {
+		self.randomness = randomness.max(0);
+	}
+
+	/// Forgets everything learned in previous searches, for when a new game starts
+	pub fn clear(&mut self) {
+		self.tt.clear();
+		self.killers = [[None; 2]; MAX_PLY + 1];
+		*self.history = [[[0; 64]; 64]; 2];
+	}
+
+	/// Setting the returned flag ends a running search on another thread as soon as possible.
+	/// A set flag also ends every later search right away, so it has to be cleared before the next search is started.
+	/// The search doesn't clear it itself, or a stop sent just before it started would be lost
+	pub fn stop_handle(&self) -> Arc<AtomicBool> {
+		self.stop.clone()
+	}
+
+	pub fn search(&mut self, board: &mut Board, limits: SearchLimits) -> SearchResult {
+		self.search_with(board, limits, |_| {})
+	}
+
+	/// Like [`Engine::search`], calling `on_iteration` with the result of every completed iteration
+	pub fn search_with(
+		&mut self,
+		board: &mut Board,
+		limits: SearchLimits,
+		mut on_iteration: impl FnMut(&SearchResult),
+	) -> SearchResult {
+		self.limits = limits;
+		self.start = Instant::now();
+		self.stopped = false;
+		self.nodes = 0;
+		self.killers = [[None; 2]; MAX_PLY + 1];
+		self.path.clone_from(&self.previous_positions);
+		self.seed = next_random(&mut self.rng);
+
+		let max_depth = limits
+			.depth
+			.unwrap_or(MAX_PLY as u32)
+			.clamp(1, MAX_PLY as u32);
+		let mut result = SearchResult::default();
+
+		for depth in 1..=max_depth {
+			let score = self.negamax(board, depth, -INFINITY, INFINITY, 0);
+			if self.stopped {
+				// the interrupted iteration has no score, but without any completed one there still has to be a move
+				// to play: the best one searched so far, or the one that would have been searched first
+				if depth == 1 {
+					let best_move = self.pv[0]
+						.first()
+						.copied()
+						.or_else(|| self.first_move(board));
+					result = SearchResult {
+						best_move,
+						pv: best_move.into_iter().collect(),
+						nodes: self.nodes,
+						..SearchResult::default()
+					};
+				}
+				break;
+			}
+
+			result = SearchResult {
+				best_move: self.pv[0].first().copied(),
+				score: Some(score),
+				depth,
+				pv: self.pv[0].clone(),
+				nodes: self.nodes,
+			};
+			on_iteration(&result);
+
+			// without legal moves or after finding the shortest mate, searching deeper won't change anything
+			if result.best_move.is_none() || score.abs() > MATE_THRESHOLD {
+				break;
+			}
+			// the next iteration takes longer than all previous ones together, so it probably wouldn't finish anyway
+			if let Some(time) = limits.time
+				&& self.start.elapsed() > time / 2
+			{
+				break;
+			}
+		}
+
+		result
+	}
+
+	/// The legal move the search would have tried first
+	fn first_move(&self, board: &Board) -> Option<Move> {
+		let mut moves = board.get_all_legal_moves();
+		let tt_move = self.tt.probe(board.hash()).and_then(|e| e.best_move);
+		self.order_moves(board, &mut moves, tt_move, 0);
+		moves.first().copied()
+	}
+
+	/// The evaluation from the point of view of the side to move
+	fn evaluate(&self, board: &Board) -> i32 {
+		let score = self.weights.evaluate(board);
+		match board.get_side_to_move() {
+			Color::White => score,
+			Color::Black => -score,
+		}
+	}
+
+	fn check_limits(&mut self) {
+		let out_of_nodes = self.limits.nodes.is_some_and(|n| self.nodes >= n);
+		let out_of_time = self.nodes.is_multiple_of(CHECK_INTERVAL)
+			&& self.limits.time.is_some_and(|t| self.start.elapsed() >= t);
+		if out_of_nodes || out_of_time || self.stop.load(Ordering::Relaxed) {
+			self.stopped = true;
+		}
+	}
+
+	/// Whether the position occurred before, since the last capture or pawn move made repetitions impossible
+	fn is_repetition(&self, board: &Board) -> bool {
+		let hash = board.hash();
+		// the last entry is the parent, with the other side to move
+		self.path
+			.iter()
+			.rev()
+			.take(board.get_halfmove_clock() as usize)
+			.skip(1)
+			.step_by(2)
+			.any(|&h| h == hash)
+	}
+
+	fn negamax(
+		&mut self,
+		board: &mut Board,
+		depth: u32,
+		mut alpha: i32,
+		beta: i32,
+		ply: usize,
+	) -> i32 {
+		self.pv[ply].clear();
+
+		if ply > 0
+			&& (board.get_halfmove_clock() >= 100
+				|| self.is_repetition(board)
+				|| board.has_insufficient_material())
+		{
+			return 0;
+		}
+
+		self.nodes += 1;
+		self.check_limits();
+		if self.stopped {
+			return 0;
+		}
+		if ply >= MAX_PLY {
+			return self.evaluate(board);
+		}
+
+		let col = board.get_side_to_move();
+		let in_check = board.is_in_check(col);
+		// don't let a check push a threat beyond the horizon
+		let depth = if in_check { depth + 1 } else { depth };
+		if depth == 0 {
+			return self.quiescence(board, alpha, beta, ply);
+		}
+
+		let key = board.hash();
+		let entry = self.tt.probe(key);
+		if let Some(entry) = entry
+			&& ply > 0
+			&& entry.depth >= depth
+		{
+			let score = score_from_tt(entry.score, ply);
+			match entry.bound {
+				Bound::Exact => return score,
+				Bound::Lower if score >= beta => return score,
+				Bound::Upper if score <= alpha => return score,
+				_ => {}
+			}
+		}
+
+		let mut moves = board.get_all_legal_moves();
+		if moves.is_empty() {
+			return if in_check { -MATE + ply as i32 } else { 0 };
+		}
+		self.order_moves(board, &mut moves, entry.and_then(|e| e.best_move), ply);
+
+		let original_alpha = alpha;
+		let mut best_score = -INFINITY;
+		let mut best_move = None;
+
+		self.path.push(key);
+		for mv in moves {
+			let undo = board.make_move(mv);
+			let score = if ply == 0 && self.randomness > 0 {
+				// the bonus can only be compared with exact scores
+				let score = -self.negamax(board, depth - 1, -INFINITY, INFINITY, ply + 1);
+				if score.abs() < MATE_THRESHOLD {
+					score + self.root_bonus(mv)
+				} else {
+					score
+				}
+			} else {
+				-self.negamax(board, depth - 1, -beta, -alpha, ply + 1)
+			};
+			board.unmake_move(undo);
+
+			if self.stopped {
+				self.path.pop();
+				return 0;
+			}
+
+			if score > best_score {
+				best_score = score;
+				best_move = Some(mv);
+			}
+			if score > alpha {
+				alpha = score;
+				self.update_pv(ply, mv);
+			}
+			if score >= beta {
+				if !mv.is_capture() && mv.get_promotion().is_none() {
+					self.record_cutoff(col, mv, depth, ply);
+				}
+				break;
+			}
+		}
+		self.path.pop();
+
+		let bound = if best_score >= beta {
+			Bound::Lower
+		} else if best_score > original_alpha {
+			Bound::Exact
+		} else {
+			Bound::Upper
+		};
+		self.tt.store(TtEntry {
+			key,
+			best_move,
+			score: score_to_tt(best_score, ply),
+			depth,
+			bound,
+		});
+
+		best_score
+	}
+
+	/// A random number between 0 and the randomness, the same for `mv` throughout one search
+	fn root_bonus(&self, mv: Move) -> i32 {
+		let mut state =
+			self.seed ^ (u64::from(mv.get_from().index()) << 6 | u64::from(mv.get_to().index()));
+		(next_random(&mut state) % (self.randomness as u64 + 1)) as i32
+	}
+
+	/// Only searches captures and promotions until the position is quiet, so the evaluation isn't taken in the middle of an exchange
+	fn quiescence(&mut self, board: &mut Board, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+		self.pv[ply].clear();
+
+		self.nodes += 1;
+		self.check_limits();
+		if self.stopped {
+			return 0;
+		}
+
+		let stand_pat = self.evaluate(board);
+		if ply >= MAX_PLY || stand_pat >= beta {
+			return stand_pat;
+		}
+		alpha = alpha.max(stand_pat);
+
+		let mut moves: Vec<Move> = board
+			.get_all_legal_moves()
+			.into_iter()
+			.filter(|mv| mv.is_capture() || mv.get_promotion().is_some())
+			.collect();
+		moves.sort_by_cached_key(|&mv| Reverse(mvv_lva(board, mv)));
+
+		for mv in moves {
+			let undo = board.make_move(mv);
+			let score = -self.quiescence(board, -beta, -alpha, ply + 1);
+			board.unmake_move(undo);
+
+			if self.stopped {
+				return 0;
+			}
+			if score >= beta {
+				return score;
+			}
+			alpha = alpha.max(score);
+		}
+
+		alpha
+	}
+
+	fn update_pv(&mut self, ply: usize, mv: Move) {
+		let (current, deeper) = self.pv.split_at_mut(ply + 1);
+		current[ply].clear();
+		current[ply].push(mv);
+		current[ply].extend_from_slice(&deeper[0]);
+	}
+
+	fn record_cutoff(&mut self, col: Color, mv: Move, depth: u32, ply: usize) {
+		let killers = &mut self.killers[ply];
+		if killers[0] != Some(mv) {
+			killers[1] = killers[0];
+			killers[0] = Some(mv);
+		}
+
+		let history = &mut self.history[color_index(col)];
+		let from = mv.get_from().index() as usize;
+		let to = mv.get_to().index() as usize;
+		history[from][to] += (depth * depth) as i32;
+		// keep history scores below the killers
+		if history[from][to] > 50_000 {
+			history.iter_mut().flatten().for_each(|h| *h /= 2);
+		}
+	}
+
+	/// Tries the transposition table move first, then captures, promotions, killers and finally quiet moves by history
+	fn order_moves(&self, board: &Board, moves: &mut [Move], tt_move: Option<Move>, ply: usize) {
+		let col = color_index(board.get_side_to_move());
+		moves.sort_by_cached_key(|&mv| {
+			let score = if Some(mv) == tt_move {
+				1_000_000
+			} else if mv.is_capture() {
+				200_000 + mvv_lva(board, mv)
+			} else if mv.get_promotion().is_some() {
+				150_000
+			} else if self.killers[ply][0] == Some(mv) {
+				100_000
+			} else if self.killers[ply][1] == Some(mv) {
+				90_000
+			} else {
+				self.history[col][mv.get_from().index() as usize][mv.get_to().index() as usize]
+			};
+			Reverse(score)
+		});
+	}
+}
+
+/// Most valuable victim, least valuable attacker: taking a queen with a pawn is tried before taking a pawn with a queen
+fn mvv_lva(board: &Board, mv: Move) -> i32 {
+	let victim = if mv.is_en_passant() {
+		Some(PieceType::Pawn)
+	} else {
+		board[mv.get_to()].get_type()
+	};
+	let attacker = board[mv.get_from()].get_type().map_or(0, |pt| pt as i32);
+	victim.map_or(0, |pt| pt as i32 * 10) - attacker
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::HashSet,
+		time::{Duration, Instant},
+	};
+
+	use crate::{
+		board::Board,
+		engine::{Engine, MATE, SearchLimits},
+		square::Square,
+	};
+
+	#[test]
+	fn test_mate_in_one() {
+		let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+		let result = Engine::new().search(&mut board, SearchLimits::depth(4));
+
+		let best = result.get_best_move().unwrap();
+		assert_eq!((best.get_from(), best.get_to()), (Square::A1, Square::A8));
+		assert_eq!(result.get_score(), Some(MATE - 1));
+		assert_eq!(result.get_mate(), Some(1));
+		// the search stops as soon as the mate is found
+		assert_eq!(result.get_depth(), 1);
+	}
+
+	#[test]
+	fn test_wins_material() {
+		let mut board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+		let result = Engine::new().search(&mut board, SearchLimits::depth(3));
+
+		let best = result.get_best_move().unwrap();
+		assert_eq!((best.get_from(), best.get_to()), (Square::D2, Square::D5));
+		assert!(result.get_score().unwrap() > 400);
+	}
+
+	#[test]
+	fn test_no_legal_moves() {
+		let mut mated =
+			Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+				.unwrap();
+		let result = Engine::new().search(&mut mated, SearchLimits::depth(3));
+		assert_eq!(result.get_best_move(), None);
+		assert_eq!(result.get_score(), Some(-MATE));
+		assert_eq!(result.get_mate(), Some(0));
+
+		let mut stalemate = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+		let result = Engine::new().search(&mut stalemate, SearchLimits::depth(3));
+		assert_eq!(result.get_best_move(), None);
+		assert_eq!(result.get_score(), Some(0));
+	}
+
+	#[test]
+	fn test_pv_is_playable() {
+		let mut board = Board::new();
+		let mut engine = Engine::new();
+		let mut depths = Vec::new();
+		let result = engine.search_with(&mut board, SearchLimits::depth(3), |r| {
+			depths.push(r.get_depth())
+		});
+		assert_eq!(depths, vec![1, 2, 3]);
+		assert_eq!(board, Board::new());
+
+		assert_eq!(result.get_pv().first().copied(), result.get_best_move());
+		for &mv in result.get_pv() {
+			assert!(board.get_all_legal_moves().contains(&mv), "{mv}");
+			board.make_move(mv);
+		}
+	}
+
+	#[test]
+	fn test_limits() {
+		let mut board = Board::new();
+		let mut engine = Engine::new();
+
+		// the attack tables are built on first use, which shouldn't count against the time limit
+		board.get_all_legal_moves();
+		let start = Instant::now();
+		let result = engine.search(&mut board, SearchLimits::time(Duration::from_millis(100)));
+		assert!(start.elapsed() < Duration::from_secs(2));
+		assert!(result.get_best_move().is_some());
+
+		let nodes = SearchLimits {
+			nodes: Some(500),
+			..SearchLimits::default()
+		};
+		let result = engine.search(&mut board, nodes);
+		assert!(result.get_best_move().is_some());
+		assert!(result.get_nodes() <= 500);
+
+		// even when stopped before a single root move was searched, there is a move to play
+		for n in [1, 5, 15] {
+			let nodes = SearchLimits {
+				nodes: Some(n),
+				..SearchLimits::default()
+			};
+			let result = Engine::new().search(&mut board, nodes);
+			let best = result.get_best_move().unwrap();
+			assert!(board.get_all_legal_moves().contains(&best));
+			assert_eq!(result.get_pv(), [best]);
+			// but nothing is known about its score
+			assert_eq!(result.get_depth(), 0);
+			assert_eq!(result.get_score(), None);
+			assert_eq!(result.get_mate(), None);
+		}
+	}
+
+	#[test]
+	fn test_randomness() {
+		let mut board = Board::new();
+		let mut engine = Engine::new();
+		engine.set_randomness(50);
+
+		// plenty of opening moves are within 50 centipawns of each other
+		let mut best_moves = HashSet::new();
+		for _ in 0..10 {
+			let result = engine.search(&mut board, SearchLimits::depth(2));
+			best_moves.insert(result.get_best_move().unwrap());
+		}
+		assert!(best_moves.len() > 1);
+
+		// but a mate isn't given up for a random bonus
+		let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+		engine.set_randomness(1000);
+		for _ in 0..5 {
+			let result = engine.search(&mut board, SearchLimits::depth(2));
+			assert_eq!(result.get_mate(), Some(1));
+		}
+	}
+}
@@ -0,0 +1,531 @@
+use std::{
+	fmt::Display,
+	ops::{AddAssign, Mul, Sub},
+	path::Path,
+	sync::LazyLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	attacks,
+	bitboard::BitBoard,
+	board::Board,
+	piece::{Color, PieceType},
+	square::Square,
+};
+
+/// A value for the middlegame and one for the endgame, which are blended by how much material is left
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+	pub mg: i32,
+	pub eg: i32,
+}
+
+impl Score {
+	pub const fn new(mg: i32, eg: i32) -> Self {
+		Self { mg, eg }
+	}
+}
+
+impl AddAssign for Score {
+	fn add_assign(&mut self, rhs: Self) {
+		self.mg += rhs.mg;
+		self.eg += rhs.eg;
+	}
+}
+
+impl Sub for Score {
+	type Output = Score;
+
+	fn sub(self, rhs: Self) -> Self::Output {
+		Score::new(self.mg - rhs.mg, self.eg - rhs.eg)
+	}
+}
+
+impl Mul<i32> for Score {
+	type Output = Score;
+
+	fn mul(self, rhs: i32) -> Self::Output {
+		Score::new(self.mg * rhs, self.eg * rhs)
+	}
+}
+
+/// A bonus for every square, laid out like a diagram seen from white, with the 8th rank first
+pub type Table = [[i32; 8]; 8];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum WeightsError {
+	Io(String),
+	Json(String),
+}
+
+impl Display for WeightsError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WeightsError::Io(e) => write!(f, "couldn't access the weights file: {e}"),
+			WeightsError::Json(e) => write!(f, "invalid weights: {e}"),
+		}
+	}
+}
+
+impl std::error::Error for WeightsError {}
+
+impl From<std::io::Error> for WeightsError {
+	fn from(value: std::io::Error) -> Self {
+		WeightsError::Io(value.to_string())
+	}
+}
+
+impl From<serde_json::Error> for WeightsError {
+	fn from(value: serde_json::Error) -> Self {
+		WeightsError::Json(value.to_string())
+	}
+}
+
+/// Every term of the evaluation in centipawns. Arrays by piece type go from pawn to king.
+/// Missing fields in a weights file keep their default value, so a file only needs the terms being tuned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Weights {
+	pub material: [Score; 6],
+	pub pst_mg: [Table; 6],
+	pub pst_eg: [Table; 6],
+	/// Per legal move of a piece
+	pub mobility: [Score; 6],
+	/// Per pawn beyond the first on a file
+	pub doubled_pawn: Score,
+	/// Per pawn without friendly pawns on the neighbouring files
+	pub isolated_pawn: Score,
+	/// By rank as seen from the pawn's side, the first entry being its own back rank
+	pub passed_pawn: [Score; 8],
+	/// Per friendly pawn on the two ranks in front of the king, on its file or a neighbouring one
+	pub pawn_shield: Score,
+	/// Per file around the king without a friendly pawn
+	pub king_open_file: Score,
+	/// Per attack of an enemy knight, bishop, rook or queen on the king or the squares next to it
+	pub king_attack: Score,
+	pub bishop_pair: Score,
+}
+
+#[rustfmt::skip]
+const PAWN_MG: Table = [
+	[  0,   0,   0,   0,   0,   0,   0,   0],
+	[ 50,  50,  50,  50,  50,  50,  50,  50],
+	[ 10,  10,  20,  30,  30,  20,  10,  10],
+	[  5,   5,  10,  25,  25,  10,   5,   5],
+	[  0,   0,   0,  20,  20,   0,   0,   0],
+	[  5,  -5, -10,   0,   0, -10,  -5,   5],
+	[  5,  10,  10, -20, -20,  10,  10,   5],
+	[  0,   0,   0,   0,   0,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const PAWN_EG: Table = [
+	[  0,   0,   0,   0,   0,   0,   0,   0],
+	[ 80,  80,  80,  80,  80,  80,  80,  80],
+	[ 50,  50,  50,  50,  50,  50,  50,  50],
+	[ 30,  30,  30,  30,  30,  30,  30,  30],
+	[ 20,  20,  20,  20,  20,  20,  20,  20],
+	[ 10,  10,  10,  10,  10,  10,  10,  10],
+	[  5,   5,   5,   5,   5,   5,   5,   5],
+	[  0,   0,   0,   0,   0,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const KNIGHT: Table = [
+	[-50, -40, -30, -30, -30, -30, -40, -50],
+	[-40, -20,   0,   0,   0,   0, -20, -40],
+	[-30,   0,  10,  15,  15,  10,   0, -30],
+	[-30,   5,  15,  20,  20,  15,   5, -30],
+	[-30,   0,  15,  20,  20,  15,   0, -30],
+	[-30,   5,  10,  15,  15,  10,   5, -30],
+	[-40, -20,   0,   5,   5,   0, -20, -40],
+	[-50, -40, -30, -30, -30, -30, -40, -50],
+];
+
+#[rustfmt::skip]
+const BISHOP: Table = [
+	[-20, -10, -10, -10, -10, -10, -10, -20],
+	[-10,   0,   0,   0,   0,   0,   0, -10],
+	[-10,   0,   5,  10,  10,   5,   0, -10],
+	[-10,   5,   5,  10,  10,   5,   5, -10],
+	[-10,   0,  10,  10,  10,  10,   0, -10],
+	[-10,  10,  10,  10,  10,  10,  10, -10],
+	[-10,   5,   0,   0,   0,   0,   5, -10],
+	[-20, -10, -10, -10, -10, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const ROOK_MG: Table = [
+	[  0,   0,   0,   0,   0,   0,   0,   0],
+	[  5,  10,  10,  10,  10,  10,  10,   5],
+	[ -5,   0,   0,   0,   0,   0,   0,  -5],
+	[ -5,   0,   0,   0,   0,   0,   0,  -5],
+	[ -5,   0,   0,   0,   0,   0,   0,  -5],
+	[ -5,   0,   0,   0,   0,   0,   0,  -5],
+	[ -5,   0,   0,   0,   0,   0,   0,  -5],
+	[  0,   0,   0,   5,   5,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const QUEEN: Table = [
+	[-20, -10, -10,  -5,  -5, -10, -10, -20],
+	[-10,   0,   0,   0,   0,   0,   0, -10],
+	[-10,   0,   5,   5,   5,   5,   0, -10],
+	[ -5,   0,   5,   5,   5,   5,   0,  -5],
+	[  0,   0,   5,   5,   5,   5,   0,  -5],
+	[-10,   5,   5,   5,   5,   5,   0, -10],
+	[-10,   0,   5,   0,   0,   0,   0, -10],
+	[-20, -10, -10,  -5,  -5, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const KING_MG: Table = [
+	[-30, -40, -40, -50, -50, -40, -40, -30],
+	[-30, -40, -40, -50, -50, -40, -40, -30],
+	[-30, -40, -40, -50, -50, -40, -40, -30],
+	[-30, -40, -40, -50, -50, -40, -40, -30],
+	[-20, -30, -30, -40, -40, -30, -30, -20],
+	[-10, -20, -20, -20, -20, -20, -20, -10],
+	[ 20,  20,   0,   0,   0,   0,  20,  20],
+	[ 20,  30,  10,   0,   0,  10,  30,  20],
+];
+
+#[rustfmt::skip]
+const KING_EG: Table = [
+	[-50, -40, -30, -20, -20, -30, -40, -50],
+	[-30, -20, -10,   0,   0, -10, -20, -30],
+	[-30, -10,  20,  30,  30,  20, -10, -30],
+	[-30, -10,  30,  40,  40,  30, -10, -30],
+	[-30, -10,  30,  40,  40,  30, -10, -30],
+	[-30, -10,  20,  30,  30,  20, -10, -30],
+	[-30, -30,   0,   0,   0,   0, -30, -30],
+	[-50, -30, -30, -30, -30, -30, -30, -50],
+];
+
+impl Default for Weights {
+	fn default() -> Self {
+		Self {
+			material: [
+				Score::new(100, 120),
+				Score::new(320, 300),
+				Score::new(330, 320),
+				Score::new(500, 530),
+				Score::new(900, 950),
+				Score::new(0, 0),
+			],
+			pst_mg: [PAWN_MG, KNIGHT, BISHOP, ROOK_MG, QUEEN, KING_MG],
+			pst_eg: [PAWN_EG, KNIGHT, BISHOP, [[0; 8]; 8], QUEEN, KING_EG],
+			mobility: [
+				Score::new(0, 0),
+				Score::new(4, 4),
+				Score::new(5, 5),
+				Score::new(2, 4),
+				Score::new(1, 2),
+				Score::new(0, 0),
+			],
+			doubled_pawn: Score::new(-10, -20),
+			isolated_pawn: Score::new(-10, -15),
+			passed_pawn: [
+				Score::new(0, 0),
+				Score::new(5, 10),
+				Score::new(10, 20),
+				Score::new(15, 35),
+				Score::new(25, 60),
+				Score::new(40, 100),
+				Score::new(60, 150),
+				Score::new(0, 0),
+			],
+			pawn_shield: Score::new(10, 0),
+			king_open_file: Score::new(-20, 0),
+			king_attack: Score::new(-8, 0),
+			bishop_pair: Score::new(30, 50),
+		}
+	}
+}
+
+static DEFAULT_WEIGHTS: LazyLock<Weights> = LazyLock::new(Weights::default);
+
+/// How much each piece counts towards the middlegame. With all of them on the board the phase is [`MAX_PHASE`]
+const PHASE_WEIGHTS: [(PieceType, i32); 4] = [
+	(PieceType::Knight, 1),
+	(PieceType::Bishop, 1),
+	(PieceType::Rook, 2),
+	(PieceType::Queen, 4),
+];
+const MAX_PHASE: i32 = 24;
+
+const PIECE_TYPES: [PieceType; 6] = [
+	PieceType::Pawn,
+	PieceType::Knight,
+	PieceType::Bishop,
+	PieceType::Rook,
+	PieceType::Queen,
+	PieceType::King,
+];
+
+/// Evaluates `board` with the default weights, see [`Weights::evaluate`]
+pub fn evaluate(board: &Board) -> i32 {
+	DEFAULT_WEIGHTS.evaluate(board)
+}
+
+/// The files next to the file of `sq`, without that file itself
+fn adjacent_files(sq: Square) -> BitBoard {
+	let file = BitBoard::FILES[sq.file().index() as usize];
+	file.east() | file.west()
+}
+
+/// The ranks in front of `sq` as seen from `col`
+fn ranks_ahead(sq: Square, col: Color) -> BitBoard {
+	let rank = sq.rank().index() as usize;
+	let ranks = match col {
+		Color::White => &BitBoard::RANKS[rank + 1..],
+		Color::Black => &BitBoard::RANKS[..rank],
+	};
+	ranks.iter().fold(BitBoard::EMPTY, |acc, &r| acc | r)
+}
+
+/// The rank of `sq` counted from `col`'s back rank, from 0 to 7
+fn relative_rank(sq: Square, col: Color) -> usize {
+	match col {
+		Color::White => sq.rank().index() as usize,
+		Color::Black => 7 - sq.rank().index() as usize,
+	}
+}
+
+impl Weights {
+	pub fn load(path: impl AsRef<Path>) -> Result<Weights, WeightsError> {
+		let json = std::fs::read_to_string(path)?;
+		Ok(serde_json::from_str(&json)?)
+	}
+
+	/// Writes every weight, which is a good starting point for a file to tune
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WeightsError> {
+		std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+		Ok(())
+	}
+
+	/// The score of `board` in centipawns from white's point of view, positive if white is better
+	pub fn evaluate(&self, board: &Board) -> i32 {
+		let score =
+			self.evaluate_side(board, Color::White) - self.evaluate_side(board, Color::Black);
+		let phase = Self::phase(board);
+		(score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE
+	}
+
+	/// From [`MAX_PHASE`] with all pieces on the board down to 0 with only kings and pawns left
+	fn phase(board: &Board) -> i32 {
+		let phase: i32 = PHASE_WEIGHTS
+			.iter()
+			.map(|&(pt, weight)| board.piece_type_occupancy(pt).size() as i32 * weight)
+			.sum();
+		phase.min(MAX_PHASE)
+	}
+
+	fn evaluate_side(&self, board: &Board, col: Color) -> Score {
+		let mut score = Score::default();
+
+		for (i, pt) in PIECE_TYPES.into_iter().enumerate() {
+			for sq in board.pieces(col, pt) {
+				score += self.material[i];
+
+				let row = match col {
+					Color::White => sq.index() / 8,
+					Color::Black => 7 - sq.index() / 8,
+				} as usize;
+				let file = sq.file().index() as usize;
+				score += Score::new(self.pst_mg[i][row][file], self.pst_eg[i][row][file]);
+
+				if self.mobility[i] != Score::default() {
+					let moves = board.get_mobility(sq).size() as i32;
+					score += self.mobility[i] * moves;
+				}
+			}
+		}
+
+		if board.pieces(col, PieceType::Bishop).size() >= 2 {
+			score += self.bishop_pair;
+		}
+
+		score += self.pawn_structure(board, col);
+		score += self.king_safety(board, col);
+		score
+	}
+
+	fn pawn_structure(&self, board: &Board, col: Color) -> Score {
+		let mut score = Score::default();
+		let pawns = board.pieces(col, PieceType::Pawn);
+		let enemy_pawns = board.pieces(col.opposite(), PieceType::Pawn);
+
+		for file in BitBoard::FILES {
+			let count = (pawns & file).size() as i32;
+			if count > 1 {
+				score += self.doubled_pawn * (count - 1);
+			}
+		}
+
+		for sq in pawns {
+			let neighbours = adjacent_files(sq);
+			if (pawns & neighbours).is_empty() {
+				score += self.isolated_pawn;
+			}
+
+			let file = BitBoard::FILES[sq.file().index() as usize];
+			let blockers = (file | neighbours) & ranks_ahead(sq, col);
+			if (enemy_pawns & blockers).is_empty() {
+				score += self.passed_pawn[relative_rank(sq, col)];
+			}
+		}
+
+		score
+	}
+
+	fn king_safety(&self, board: &Board, col: Color) -> Score {
+		let mut score = Score::default();
+		let Some(king) = board.pieces(col, PieceType::King).lsb() else {
+			return score;
+		};
+
+		let pawns = board.pieces(col, PieceType::Pawn);
+		let files = BitBoard::FILES[king.file().index() as usize] | adjacent_files(king);
+		let forward = match col {
+			Color::White => 1,
+			Color::Black => -1,
+		};
+		let shield_ranks = (1..=2)
+			.filter_map(|d| king.offset(0, d * forward))
+			.fold(BitBoard::EMPTY, |acc, sq| {
+				acc | BitBoard::RANKS[sq.rank().index() as usize]
+			});
+		score += self.pawn_shield * (pawns & files & shield_ranks).size() as i32;
+
+		for file in BitBoard::FILES {
+			if !(file & files).is_empty() && (file & pawns).is_empty() {
+				score += self.king_open_file;
+			}
+		}
+
+		let zone = attacks::king_attacks(king) | BitBoard::square(king);
+		let occupation = board.occupancy();
+		let enemy = col.opposite();
+		let mut attacks = 0;
+		for sq in board.pieces(enemy, PieceType::Knight) {
+			attacks += (attacks::knight_attacks(sq) & zone).size();
+		}
+		for sq in board.pieces(enemy, PieceType::Bishop) {
+			attacks += (attacks::bishop_attacks(sq, occupation) & zone).size();
+		}
+		for sq in board.pieces(enemy, PieceType::Rook) {
+			attacks += (attacks::rook_attacks(sq, occupation) & zone).size();
+		}
+		for sq in board.pieces(enemy, PieceType::Queen) {
+			attacks += (attacks::queen_attacks(sq, occupation) & zone).size();
+		}
+		score += self.king_attack * attacks as i32;
+
+		score
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		eval::{Score, Weights, WeightsError, evaluate},
+	};
+
+	/// The same position with the colors swapped and the board flipped
+	fn mirror(fen: &str) -> String {
+		let fields: Vec<&str> = fen.split(' ').collect();
+		let swap_case = |s: &str| -> String {
+			s.chars()
+				.map(|c| {
+					if c.is_ascii_uppercase() {
+						c.to_ascii_lowercase()
+					} else {
+						c.to_ascii_uppercase()
+					}
+				})
+				.collect()
+		};
+
+		let placement: Vec<String> = fields[0].split('/').rev().map(swap_case).collect();
+		let side = if fields[1] == "w" { "b" } else { "w" };
+		let en_passant = match fields[3] {
+			"-" => "-".to_string(),
+			ep => format!("{}{}", &ep[..1], if &ep[1..] == "3" { 6 } else { 3 }),
+		};
+		format!(
+			"{} {side} {} {en_passant} 0 1",
+			placement.join("/"),
+			swap_case(fields[2])
+		)
+	}
+
+	#[test]
+	fn test_symmetry() {
+		assert_eq!(evaluate(&Board::new()), 0);
+
+		for fen in [
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+			"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+			"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+		] {
+			let board = Board::from_fen(fen).unwrap();
+			let mirrored = Board::from_fen(&mirror(fen)).unwrap();
+			assert_eq!(evaluate(&board), -evaluate(&mirrored), "{fen}");
+		}
+	}
+
+	#[test]
+	fn test_terms() {
+		// a missing queen outweighs everything else
+		let without_queen =
+			Board::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+		assert!(evaluate(&without_queen) > 700);
+
+		// a passed pawn is worth more than one that is blocked by an enemy pawn ahead of it
+		let passed = Board::from_fen("4k3/8/8/1P6/8/8/8/4K3 w - - 0 1").unwrap();
+		let blocked = Board::from_fen("4k3/1p6/8/1P6/8/8/8/4K3 w - - 0 1").unwrap();
+		let black_pawn = Board::from_fen("4k3/1p6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+		assert!(evaluate(&passed) > evaluate(&blocked) - evaluate(&black_pawn));
+
+		// two pawns side by side beat doubled ones
+		let connected = Board::from_fen("4k3/8/8/8/8/2PP4/8/4K3 w - - 0 1").unwrap();
+		let doubled = Board::from_fen("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1").unwrap();
+		assert!(evaluate(&connected) > evaluate(&doubled));
+
+		// with only two bishops left the bonus is almost entirely the endgame value
+		let pair = Board::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+		let weights = Weights {
+			bishop_pair: Score::new(0, 0),
+			..Weights::default()
+		};
+		let bonus = Weights::default().bishop_pair;
+		assert!((bonus.mg..=bonus.eg).contains(&(evaluate(&pair) - weights.evaluate(&pair))));
+	}
+
+	#[test]
+	fn test_load_weights() {
+		let weights: Weights =
+			serde_json::from_str(r#"{ "bishop_pair": { "mg": 1, "eg": 2 } }"#).unwrap();
+		assert_eq!(weights.bishop_pair, Score::new(1, 2));
+		assert_eq!(weights.material, Weights::default().material);
+
+		// test runs in parallel must not share the file
+		let dir = std::env::temp_dir();
+		let path = dir.join(format!("chess-rs-weights-{}.json", std::process::id()));
+		Weights::default().save(&path).unwrap();
+		assert_eq!(Weights::load(&path).unwrap(), Weights::default());
+
+		std::fs::write(&path, "{ not json").unwrap();
+		assert!(matches!(Weights::load(&path), Err(WeightsError::Json(_))));
+		assert!(matches!(
+			Weights::load(dir.join("chess-rs-missing.json")),
+			Err(WeightsError::Io(_))
+		));
+		std::fs::remove_file(path).unwrap();
+	}
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use crate::{
+	board::Board,
+	engine::SearchResult,
+	game::{Game, Outcome},
+	registry::GameId,
+};
+
+/// A payload the backend pushes to the frontend, which listens for it under [`Event::NAME`]
+pub trait Event: Serialize + Clone {
+	const NAME: &'static str;
+}
+
+/// The board of a game changed, through a move, taking one back or loading a new position
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BoardChanged {
+	pub game: GameId,
+	pub board: Board,
+	pub fen: String,
+	/// How many moves are played on the board
+	pub ply: usize,
+}
+
+impl BoardChanged {
+	pub fn new(id: GameId, game: &Game) -> Self {
+		Self {
+			game: id,
+			board: game.get_board().clone(),
+			fen: game.get_board().to_fen(),
+			ply: game.get_cursor(),
+		}
+	}
+}
+
+impl Event for BoardChanged {
+	const NAME: &'static str = "board-changed";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GameOver {
+	pub game: GameId,
+	pub outcome: Outcome,
+}
+
+impl Event for GameOver {
+	const NAME: &'static str = "game-over";
+}
+
+/// The time both sides have left, in milliseconds. Nothing sends it until the app has a clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ClockTick {
+	pub game: GameId,
+	pub white: u64,
+	pub black: u64,
+}
+
+impl Event for ClockTick {
+	const NAME: &'static str = "clock-tick";
+}
+
+/// The result of one iteration of a search, like a UCI info line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EngineInfo {
+	pub game: GameId,
+	pub depth: u32,
+	/// In centipawns, from the point of view of the side to move. `None` if the search didn't complete depth 1
+	pub score: Option<i32>,
+	/// Moves until mate, negative if the side to move gets mated
+	pub mate: Option<i32>,
+	pub nodes: u64,
+	/// The principal variation in long algebraic notation
+	pub pv: Vec<String>,
+}
+
+impl EngineInfo {
+	pub fn new(id: GameId, result: &SearchResult) -> Self {
+		Self {
+			game: id,
+			depth: result.get_depth(),
+			score: result.get_score(),
+			mate: result.get_mate(),
+			nodes: result.get_nodes(),
+			pv: result.get_pv().iter().map(|mv| mv.to_string()).collect(),
+		}
+	}
+}
+
+impl Event for EngineInfo {
+	const NAME: &'static str = "engine-info";
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		engine::{Engine, SearchLimits},
+		events::{BoardChanged, EngineInfo},
+		game::Game,
+		square::Square,
+	};
+
+	#[test]
+	fn test_payloads() {
+		let mut game = Game::default();
+		game.move_piece(Square::E2.index(), Square::E4.index(), None)
+			.unwrap();
+		let changed = BoardChanged::new(3, &game);
+		assert_eq!(changed.ply, 1);
+		assert_eq!(changed.fen, game.get_board().to_fen());
+
+		let json = serde_json::to_value(&changed).unwrap();
+		assert_eq!(json["game"], 3);
+		assert_eq!(
+			json["board"],
+			serde_json::to_value(game.get_board()).unwrap()
+		);
+
+		// mate in one, which the search finds at depth 1
+		let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+		let result = Engine::new().search(&mut board, SearchLimits::depth(2));
+		let info = EngineInfo::new(1, &result);
+		assert_eq!(info.depth, 1);
+		assert_eq!(info.mate, Some(1));
+		assert_eq!(info.pv[0], "a1a8");
+	}
+}
@@ -0,0 +1,381 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{
+	board::{Board, CastlingRights},
+	piece::{ChessCell, Color, Moved, PieceType},
+	square::{File, Rank, Square},
+};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FenError {
+	/// A FEN needs at least the placement, side to move, castling and en passant fields, optionally followed by both move counters
+	FieldCount(usize),
+	RankCount(usize),
+	/// The rank (1-8) doesn't describe exactly 8 squares
+	RankLength(u8),
+	InvalidPiece(char),
+	/// Each side needs exactly one king
+	KingCount(Color),
+	InvalidSideToMove(String),
+	InvalidCastling(String),
+	InvalidEnPassant(String),
+	InvalidHalfmoveClock(String),
+	InvalidFullmoveNumber(String),
+}
+
+impl Display for FenError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			FenError::FieldCount(n) => write!(f, "expected 4 or 6 fields, found {n}"),
+			FenError::RankCount(n) => write!(f, "expected 8 ranks, found {n}"),
+			FenError::RankLength(rank) => write!(f, "rank {rank} doesn't have 8 squares"),
+			FenError::InvalidPiece(c) => write!(f, "'{c}' is not a piece"),
+			FenError::KingCount(col) => write!(f, "{col:?} needs exactly one king"),
+			FenError::InvalidSideToMove(s) => write!(f, "invalid side to move \"{s}\""),
+			FenError::InvalidCastling(s) => write!(f, "invalid castling rights \"{s}\""),
+			FenError::InvalidEnPassant(s) => write!(f, "invalid en passant square \"{s}\""),
+			FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock \"{s}\""),
+			FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number \"{s}\""),
+		}
+	}
+}
+
+impl std::error::Error for FenError {}
+
+fn parse_placement(board: &mut Board, placement: &str) -> Result<(), FenError> {
+	let ranks: Vec<&str> = placement.split('/').collect();
+	if ranks.len() != 8 {
+		return Err(FenError::RankCount(ranks.len()));
+	}
+
+	// FEN starts with the 8th rank
+	for (rank, pieces) in Rank::ALL.into_iter().rev().zip(ranks) {
+		let rank_number = rank.index() + 1;
+		let mut x: u8 = 0;
+
+		for c in pieces.chars() {
+			if let Some(empty) = c.to_digit(10) {
+				if empty == 0 || empty > 8 {
+					return Err(FenError::InvalidPiece(c));
+				}
+				// checked before adding, so a long run of digits can't overflow
+				if x + empty as u8 > 8 {
+					return Err(FenError::RankLength(rank_number));
+				}
+				x += empty as u8;
+				continue;
+			}
+
+			let Some(file) = File::new(x) else {
+				return Err(FenError::RankLength(rank_number));
+			};
+
+			let ptype = PieceType::try_from(c).map_err(|_| FenError::InvalidPiece(c))?;
+			let col = if c.is_ascii_uppercase() {
+				Color::White
+			} else {
+				Color::Black
+			};
+
+			// FEN doesn't know which pieces have moved, but pawns outside of their starting rank certainly did
+			let start_rank = match col {
+				Color::White => Rank::Two,
+				Color::Black => Rank::Seven,
+			};
+			let moved = if ptype == PieceType::Pawn && rank != start_rank {
+				Moved::Yes
+			} else {
+				Moved::No
+			};
+
+			board.add_piece(
+				ChessCell::with_piece(ptype, col, moved),
+				Square::from_coords(file, rank),
+			);
+			x += 1;
+		}
+
+		if x != 8 {
+			return Err(FenError::RankLength(rank_number));
+		}
+	}
+
+	Ok(())
+}
+
+fn parse_castling(castling: &str) -> Result<CastlingRights, FenError> {
+	let mut rights = CastlingRights::empty();
+	if castling == "-" {
+		return Ok(rights);
+	}
+
+	for c in castling.chars() {
+		let right = match c {
+			'K' => CastlingRights::WHITE_KINGSIDE,
+			'Q' => CastlingRights::WHITE_QUEENSIDE,
+			'k' => CastlingRights::BLACK_KINGSIDE,
+			'q' => CastlingRights::BLACK_QUEENSIDE,
+			_ => return Err(FenError::InvalidCastling(castling.to_string())),
+		};
+
+		if rights.contains(right) {
+			return Err(FenError::InvalidCastling(castling.to_string()));
+		}
+		rights.insert(right);
+	}
+
+	Ok(rights)
+}
+
+fn parse_en_passant(board: &Board, en_passant: &str) -> Result<Option<Square>, FenError> {
+	if en_passant == "-" {
+		return Ok(None);
+	}
+
+	let err = || FenError::InvalidEnPassant(en_passant.to_string());
+
+	let target: Square = en_passant.parse().map_err(|_| err())?;
+
+	// the skipped square is always on the 3rd rank for white and on the 6th for black
+	let (expected_rank, pawn_rank) = match board.get_side_to_move() {
+		Color::White => (Rank::Six, Rank::Five),
+		Color::Black => (Rank::Three, Rank::Four),
+	};
+	if target.rank() != expected_rank {
+		return Err(err());
+	}
+
+	// the pawn that just made the double step has to be right in front of the skipped square
+	let pawn = board[Square::from_coords(target.file(), pawn_rank)];
+	if board[target].get_type().is_some()
+		|| pawn.get_type() != Some(PieceType::Pawn)
+		|| pawn.get_color() == board.get_side_to_move()
+	{
+		return Err(err());
+	}
+
+	Ok(Some(target))
+}
+
+impl Board {
+	/// Parses a position in Forsyth-Edwards Notation.
+	/// The halfmove clock and fullmove number may be omitted, in which case they default to 0 and 1
+	pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+		let fields: Vec<&str> = fen.split_whitespace().collect();
+		if fields.len() != 4 && fields.len() != 6 {
+			return Err(FenError::FieldCount(fields.len()));
+		}
+
+		let mut board = Board::empty();
+		parse_placement(&mut board, fields[0])?;
+
+		for col in [Color::White, Color::Black] {
+			if board.pieces(col, PieceType::King).size() != 1 {
+				return Err(FenError::KingCount(col));
+			}
+		}
+
+		let side_to_move = match fields[1] {
+			"w" => Color::White,
+			"b" => Color::Black,
+			s => return Err(FenError::InvalidSideToMove(s.to_string())),
+		};
+		board.set_side_to_move(side_to_move);
+		board.set_castling_rights(parse_castling(fields[2])?);
+		board.set_en_passant(parse_en_passant(&board, fields[3])?);
+
+		if fields.len() == 6 {
+			let halfmove_clock = fields[4]
+				.parse()
+				.map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+			board.set_halfmove_clock(halfmove_clock);
+
+			let fullmove_number = fields[5]
+				.parse()
+				.ok()
+				.filter(|n| *n > 0)
+				.ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_string()))?;
+			board.set_fullmove_number(fullmove_number);
+		}
+
+		Ok(board)
+	}
+
+	/// Writes the position in Forsyth-Edwards Notation
+	pub fn to_fen(&self) -> String {
+		let mut fen = String::new();
+
+		for rank in Rank::ALL.into_iter().rev() {
+			let mut empty = 0;
+			for file in File::ALL {
+				let cell = self[Square::from_coords(file, rank)];
+				match cell.get_type() {
+					None => empty += 1,
+					Some(ptype) => {
+						if empty > 0 {
+							fen.push_str(&empty.to_string());
+							empty = 0;
+						}
+
+						let c = char::from(ptype);
+						match cell.get_color() {
+							Color::White => fen.push(c),
+							Color::Black => fen.push(c.to_ascii_lowercase()),
+						}
+					}
+				}
+			}
+
+			if empty > 0 {
+				fen.push_str(&empty.to_string());
+			}
+			if rank != Rank::One {
+				fen.push('/');
+			}
+		}
+
+		fen.push(' ');
+		fen.push(match self.get_side_to_move() {
+			Color::White => 'w',
+			Color::Black => 'b',
+		});
+
+		fen.push(' ');
+		let rights = self.get_castling_rights();
+		if rights.is_empty() {
+			fen.push('-');
+		} else {
+			for (right, c) in [
+				(CastlingRights::WHITE_KINGSIDE, 'K'),
+				(CastlingRights::WHITE_QUEENSIDE, 'Q'),
+				(CastlingRights::BLACK_KINGSIDE, 'k'),
+				(CastlingRights::BLACK_QUEENSIDE, 'q'),
+			] {
+				if rights.contains(right) {
+					fen.push(c);
+				}
+			}
+		}
+
+		fen.push(' ');
+		match self.get_en_passant() {
+			Some(i) => fen.push_str(&i.to_string()),
+			None => fen.push('-'),
+		}
+
+		fen.push_str(&format!(
+			" {} {}",
+			self.get_halfmove_clock(),
+			self.get_fullmove_number()
+		));
+
+		fen
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::{Board, CastlingRights},
+		fen::{FenError, START_FEN},
+		piece::{Color, PieceType},
+		square::Square,
+	};
+
+	#[test]
+	fn test_fen_round_trip() {
+		let positions = [
+			START_FEN,
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+			"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+			"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 b - - 17 42",
+		];
+
+		for fen in positions {
+			assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+		}
+	}
+
+	#[test]
+	fn test_fen_state() {
+		let board =
+			Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w Kq f6").unwrap();
+		assert_eq!(board.get_side_to_move(), Color::White);
+		assert_eq!(
+			board.get_castling_rights(),
+			CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_QUEENSIDE
+		);
+		assert_eq!(board.get_en_passant(), Some(Square::F6));
+		assert_eq!(board.get_halfmove_clock(), 0);
+		assert_eq!(board.get_fullmove_number(), 1);
+
+		let pawn = board[Square::F5];
+		assert!(pawn.eligable_en_pessant_take());
+
+		let king = board[Square::E1];
+		assert_eq!(king.get_type(), Some(PieceType::King));
+		assert_eq!(king.get_color(), Color::White);
+	}
+
+	#[test]
+	fn test_fen_errors() {
+		assert_eq!(
+			Board::from_fen("8/8/8/8/8/8/8/8 w - -").err(),
+			Some(FenError::KingCount(Color::White))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1").err(),
+			Some(FenError::RankCount(7))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(),
+			Some(FenError::InvalidPiece('9'))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(),
+			Some(FenError::RankLength(7))
+		);
+		assert_eq!(
+			Board::from_fen(&format!("{}/8/8/8/8/8/8/8 w - - 0 1", "8".repeat(33))).err(),
+			Some(FenError::RankLength(8))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR7 w KQkq - 0 1").err(),
+			Some(FenError::RankLength(1))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1").err(),
+			Some(FenError::InvalidPiece('X'))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").err(),
+			Some(FenError::InvalidSideToMove("x".to_string()))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkk - 0 1").err(),
+			Some(FenError::InvalidCastling("KQkk".to_string()))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1").err(),
+			Some(FenError::InvalidEnPassant("e3".to_string()))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 1").err(),
+			Some(FenError::InvalidEnPassant("e6".to_string()))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1").err(),
+			Some(FenError::InvalidHalfmoveClock("x".to_string()))
+		);
+		assert_eq!(
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0").err(),
+			Some(FenError::InvalidFullmoveNumber("0".to_string()))
+		);
+		assert_eq!(Board::from_fen("").err(), Some(FenError::FieldCount(0)));
+	}
+}
@@ -0,0 +1,513 @@
+use serde::Serialize;
+
+use crate::{
+	board::Board,
+	moves::{Move, MoveError},
+	notation::{GameResult, to_san},
+	piece::PieceType,
+	square::Square,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EndReason {
+	Checkmate,
+	Stalemate,
+	/// 50 moves by each side without a capture or pawn move
+	FiftyMoveRule,
+	/// 75 moves by each side without a capture or pawn move, which ends the game even if no one claims it
+	SeventyFiveMoveRule,
+	ThreefoldRepetition,
+	FivefoldRepetition,
+	/// Neither side has the pieces left to deliver mate
+	InsufficientMaterial,
+}
+
+impl EndReason {
+	/// Whether the game ends without anyone claiming it. The fifty-move rule and threefold repetition only allow
+	/// claiming a draw, so the game may go on
+	pub fn is_mandatory(self) -> bool {
+		!matches!(
+			self,
+			EndReason::FiftyMoveRule | EndReason::ThreefoldRepetition
+		)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Outcome {
+	pub result: GameResult,
+	pub reason: EndReason,
+}
+
+impl Outcome {
+	pub fn draw(reason: EndReason) -> Self {
+		Self {
+			result: GameResult::Draw,
+			reason,
+		}
+	}
+}
+
+/// One move of a [`History`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistoryEntry {
+	pub san: String,
+	pub from: Square,
+	pub to: Square,
+	pub promotion: Option<PieceType>,
+}
+
+/// The moves of a game and how many of them are currently played on the board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct History {
+	pub moves: Vec<HistoryEntry>,
+	pub cursor: usize,
+}
+
+/// A game played from a starting position, remembering every position it went through.
+/// Moves can be taken back and replayed, the board shows the position after the first `cursor` moves
+#[derive(Clone, Debug)]
+pub struct Game {
+	start: Board,
+	board: Board,
+	moves: Vec<Move>,
+	/// The standard algebraic notation of each move
+	sans: Vec<String>,
+	/// The position before each move
+	positions: Vec<Board>,
+	cursor: usize,
+}
+
+impl Default for Game {
+	fn default() -> Self {
+		Self::new(Board::new())
+	}
+}
+
+impl Game {
+	pub fn new(start: Board) -> Self {
+		Self {
+			board: start.clone(),
+			start,
+			moves: Vec::new(),
+			sans: Vec::new(),
+			positions: Vec::new(),
+			cursor: 0,
+		}
+	}
+
+	pub fn get_start(&self) -> &Board {
+		&self.start
+	}
+
+	/// The position at the cursor
+	pub fn get_board(&self) -> &Board {
+		&self.board
+	}
+
+	/// Every move of the game, including those after the cursor that were taken back
+	pub fn get_moves(&self) -> &[Move] {
+		&self.moves
+	}
+
+	/// How many moves are played on the board
+	pub fn get_cursor(&self) -> usize {
+		self.cursor
+	}
+
+	/// The [`Board::hash`]es of the positions before the cursor, oldest first, for [`Engine::set_previous_positions`](crate::engine::Engine::set_previous_positions)
+	pub fn position_hashes(&self) -> Vec<u64> {
+		self.positions[..self.cursor]
+			.iter()
+			.map(Board::hash)
+			.collect()
+	}
+
+	/// Plays `mv` at the cursor without checking whether it is legal. Moves that were taken back are discarded
+	pub fn play(&mut self, mv: Move) {
+		self.moves.truncate(self.cursor);
+		self.sans.truncate(self.cursor);
+		self.positions.truncate(self.cursor);
+
+		self.sans.push(to_san(
+			&self.board,
+			mv.get_from(),
+			mv.get_to(),
+			mv.get_promotion(),
+		));
+		self.positions.push(self.board.clone());
+		self.board.make_move(mv);
+		self.moves.push(mv);
+		self.cursor += 1;
+	}
+
+	/// Plays the piece on `index` to `target` if that is legal, see [`Board::validate_move`].
+	/// No move is legal anymore once the game [`is_over`](Game::is_over)
+	pub fn move_piece(
+		&mut self,
+		index: u8,
+		target: u8,
+		promotion: Option<PieceType>,
+	) -> Result<(), MoveError> {
+		if let Some(outcome) = self.status()
+			&& outcome.reason.is_mandatory()
+		{
+			return Err(MoveError::GameOver(outcome));
+		}
+		let mv = self.board.validate_move(index, target, promotion)?;
+		self.play(mv);
+		Ok(())
+	}
+
+	/// Takes back the last move on the board. Returns false if there is none
+	pub fn undo(&mut self) -> bool {
+		if self.cursor == 0 {
+			return false;
+		}
+		self.goto_ply(self.cursor - 1)
+	}
+
+	/// Plays the next move that was taken back. Returns false if there is none
+	pub fn redo(&mut self) -> bool {
+		if self.cursor == self.moves.len() {
+			return false;
+		}
+		self.goto_ply(self.cursor + 1)
+	}
+
+	/// Moves the cursor to after the first `ply` moves. Returns false if the game doesn't have that many
+	pub fn goto_ply(&mut self, ply: usize) -> bool {
+		if ply > self.moves.len() {
+			return false;
+		}
+
+		self.board = match self.positions.get(ply) {
+			Some(position) => position.clone(),
+			// after the last move, which no position was saved for
+			None => match self.positions.last() {
+				Some(position) => {
+					let mut board = position.clone();
+					board.make_move(self.moves[ply - 1]);
+					board
+				}
+				None => self.start.clone(),
+			},
+		};
+		self.cursor = ply;
+		true
+	}
+
+	pub fn history(&self) -> History {
+		let moves = self
+			.moves
+			.iter()
+			.zip(&self.sans)
+			.map(|(mv, san)| HistoryEntry {
+				san: san.clone(),
+				from: mv.get_from(),
+				to: mv.get_to(),
+				promotion: mv.get_promotion(),
+			})
+			.collect();
+		History {
+			moves,
+			cursor: self.cursor,
+		}
+	}
+
+	/// How often the current position has occurred in this game, including right now
+	pub fn repetitions(&self) -> usize {
+		// captures, pawn moves and lost castling rights can't be undone, so only positions since the last of those can repeat.
+		// Those reset the halfmove clock, apart from castling rights, which are part of the comparison anyway
+		let reversible = self.board.get_halfmove_clock() as usize;
+
+		1 + self.positions[..self.cursor]
+			.iter()
+			.rev()
+			.take(reversible)
+			.filter(|position| position.is_same_position(&self.board))
+			.count()
+	}
+
+	/// Whether and how the game has ended, including the repetition rules [`Board::status`] can't know about
+	pub fn status(&self) -> Option<Outcome> {
+		let status = self.board.status();
+		let repetitions = self.repetitions();
+
+		match status {
+			// mate and the automatic draws take precedence over anything that would have to be claimed
+			Some(Outcome {
+				reason:
+					EndReason::Checkmate
+					| EndReason::Stalemate
+					| EndReason::SeventyFiveMoveRule
+					| EndReason::InsufficientMaterial,
+				..
+			}) => status,
+			_ if repetitions >= 5 => Some(Outcome::draw(EndReason::FivefoldRepetition)),
+			Some(_) => status,
+			None if repetitions >= 3 => Some(Outcome::draw(EndReason::ThreefoldRepetition)),
+			None => None,
+		}
+	}
+
+	/// Whether the game has ended for good, rather than only allowing a draw to be claimed
+	pub fn is_over(&self) -> bool {
+		self.status()
+			.is_some_and(|outcome| outcome.reason.is_mandatory())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		game::{EndReason, Game, Outcome},
+		moves::MoveError,
+		notation::GameResult,
+		square::Square,
+	};
+
+	fn play(game: &mut Game, moves: &[&str]) {
+		for mv in moves {
+			let from = mv[..2].parse().unwrap();
+			let to = mv[2..].parse().unwrap();
+			let mv = game.get_board().create_move(from, to, None);
+			game.play(mv);
+		}
+	}
+
+	#[test]
+	fn test_checkmate() {
+		let mut game = Game::default();
+		play(&mut game, &["f2f3", "e7e5", "g2g4"]);
+		assert_eq!(game.status(), None);
+
+		play(&mut game, &["d8h4"]);
+		assert_eq!(
+			game.status(),
+			Some(Outcome {
+				result: GameResult::Black,
+				reason: EndReason::Checkmate
+			})
+		);
+	}
+
+	#[test]
+	fn test_stalemate() {
+		let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+		assert_eq!(board.status(), Some(Outcome::draw(EndReason::Stalemate)));
+	}
+
+	#[test]
+	fn test_move_rules() {
+		let board = Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+		assert_eq!(board.status(), None);
+
+		let board = Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 100 80").unwrap();
+		assert_eq!(
+			board.status(),
+			Some(Outcome::draw(EndReason::FiftyMoveRule))
+		);
+
+		let board = Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 150 80").unwrap();
+		assert_eq!(
+			board.status(),
+			Some(Outcome::draw(EndReason::SeventyFiveMoveRule))
+		);
+
+		// mate on the last move still counts
+		let board = Board::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 80").unwrap();
+		assert_eq!(board.status().unwrap().reason, EndReason::Checkmate);
+	}
+
+	#[test]
+	fn test_insufficient_material() {
+		let insufficient = [
+			"4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+			"4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+			"4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+			// bishops on the same square color
+			"4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+		];
+		for fen in insufficient {
+			let board = Board::from_fen(fen).unwrap();
+			assert!(board.has_insufficient_material(), "{fen}");
+			assert_eq!(
+				board.status(),
+				Some(Outcome::draw(EndReason::InsufficientMaterial))
+			);
+		}
+
+		let sufficient = [
+			"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+			"4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+			"4k3/8/8/8/8/8/8/3NKN2 w - - 0 1",
+			"4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+			"4kn2/8/8/8/8/8/8/4KB2 w - - 0 1",
+		];
+		for fen in sufficient {
+			let board = Board::from_fen(fen).unwrap();
+			assert!(!board.has_insufficient_material(), "{fen}");
+			assert_eq!(board.status(), None);
+		}
+	}
+
+	#[test]
+	fn test_repetition() {
+		let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+		let mut game = Game::default();
+		play(&mut game, &shuffle);
+		assert_eq!(game.repetitions(), 2);
+		assert_eq!(game.status(), None);
+
+		play(&mut game, &shuffle);
+		assert_eq!(game.repetitions(), 3);
+		assert_eq!(
+			game.status(),
+			Some(Outcome::draw(EndReason::ThreefoldRepetition))
+		);
+
+		// a draw that has to be claimed doesn't stop the game
+		assert!(!game.is_over());
+		game.move_piece(Square::G1.index(), Square::F3.index(), None)
+			.unwrap();
+		game.undo();
+
+		play(&mut game, &shuffle);
+		play(&mut game, &shuffle);
+		assert_eq!(game.repetitions(), 5);
+		let fivefold = Outcome::draw(EndReason::FivefoldRepetition);
+		assert_eq!(game.status(), Some(fivefold));
+
+		// but a mandatory one does
+		assert!(game.is_over());
+		assert_eq!(
+			game.move_piece(Square::G1.index(), Square::F3.index(), None),
+			Err(MoveError::GameOver(fivefold))
+		);
+		assert_eq!(game.repetitions(), 5);
+	}
+
+	#[test]
+	fn test_no_moves_after_mate() {
+		let mut game = Game::default();
+		play(&mut game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+		assert_eq!(
+			game.move_piece(Square::E1.index(), Square::F2.index(), None),
+			Err(MoveError::GameOver(Outcome {
+				result: GameResult::Black,
+				reason: EndReason::Checkmate
+			}))
+		);
+		assert_eq!(game.get_moves().len(), 4);
+
+		let mut game = Game::new(Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap());
+		assert_eq!(
+			game.move_piece(Square::H8.index(), Square::G8.index(), None),
+			Err(MoveError::GameOver(Outcome::draw(EndReason::Stalemate)))
+		);
+	}
+
+	#[test]
+	fn test_no_moves_after_mandatory_draw() {
+		// the capture leaves only the kings
+		let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1").unwrap());
+		game.move_piece(Square::E1.index(), Square::D2.index(), None)
+			.unwrap();
+		assert!(game.is_over());
+		assert_eq!(
+			game.move_piece(Square::E8.index(), Square::E7.index(), None),
+			Err(MoveError::GameOver(Outcome::draw(
+				EndReason::InsufficientMaterial
+			)))
+		);
+
+		let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 150 80").unwrap());
+		assert!(matches!(
+			game.move_piece(Square::A1.index(), Square::A2.index(), None),
+			Err(MoveError::GameOver(_))
+		));
+		// the fifty-move rule alone only allows a claim
+		let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 100 80").unwrap());
+		assert!(
+			game.move_piece(Square::A1.index(), Square::A2.index(), None)
+				.is_ok()
+		);
+	}
+
+	#[test]
+	fn test_repetition_needs_same_rights() {
+		// the first king move loses castling rights, so the position before it never repeats
+		let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap());
+		let shuffle = ["e1d1", "e8d8", "d1e1", "d8e8"];
+		play(&mut game, &shuffle);
+		play(&mut game, &shuffle);
+		assert_eq!(game.repetitions(), 2);
+		assert_eq!(game.status(), None);
+	}
+
+	#[test]
+	fn test_undo_redo() {
+		let mut game = Game::default();
+		assert!(!game.undo());
+		play(&mut game, &["e2e4", "e7e5", "g1f3"]);
+		let after_three = game.get_board().clone();
+
+		assert!(game.undo());
+		assert!(game.undo());
+		assert_eq!(game.get_cursor(), 1);
+		assert_eq!(
+			game.get_board().to_fen(),
+			"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+		);
+
+		assert!(game.redo());
+		assert!(game.redo());
+		assert!(!game.redo());
+		assert_eq!(game.get_board(), &after_three);
+
+		assert!(game.goto_ply(0));
+		assert_eq!(game.get_board(), &Board::new());
+		assert!(game.goto_ply(3));
+		assert_eq!(game.get_board(), &after_three);
+		assert!(!game.goto_ply(4));
+		assert_eq!(game.get_cursor(), 3);
+	}
+
+	#[test]
+	fn test_new_branch() {
+		let mut game = Game::default();
+		play(&mut game, &["e2e4", "e7e5", "g1f3"]);
+		game.goto_ply(1);
+
+		// playing a different move discards the moves after the cursor
+		play(&mut game, &["c7c5"]);
+		assert_eq!(game.get_moves().len(), 2);
+		assert!(!game.redo());
+
+		let history = game.history();
+		assert_eq!(history.cursor, 2);
+		let sans: Vec<&str> = history.moves.iter().map(|m| m.san.as_str()).collect();
+		assert_eq!(sans, ["e4", "c5"]);
+		assert_eq!(history.moves[1].from, Square::C7);
+	}
+
+	#[test]
+	fn test_san_history() {
+		let mut game = Game::default();
+		play(&mut game, &["e2e4", "f7f6", "d2d4", "g7g5", "d1h5"]);
+		let sans: Vec<String> = game.history().moves.into_iter().map(|m| m.san).collect();
+		assert_eq!(sans, ["e4", "f6", "d4", "g5", "Qh5#"]);
+
+		// repetitions only count positions up to the cursor
+		let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+		let mut game = Game::default();
+		play(&mut game, &shuffle);
+		play(&mut game, &shuffle);
+		assert_eq!(game.repetitions(), 3);
+		game.goto_ply(4);
+		assert_eq!(game.repetitions(), 2);
+	}
+}
@@ -1,7 +1,18 @@
-#![feature(iter_array_chunks)]
-#![feature(string_remove_matches)]
-
+pub mod attacks;
 pub mod bitboard;
 pub mod board;
+pub mod computer;
+pub mod engine;
+pub mod eval;
+pub mod events;
+pub mod fen;
+pub mod game;
+pub mod moves;
 pub mod notation;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
+pub mod polyglot;
+pub mod registry;
+pub mod square;
+pub mod zobrist;
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+use bitflags::bitflags;
+use serde::Serialize;
+
+use crate::{game::Outcome, piece::PieceType, square::Square};
+
+bitflags! {
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+	pub struct MoveFlags: u8 {
+		const CAPTURE = 1;
+		/// A pawn moving two squares, which allows an en passant capture in the next move
+		const DOUBLE_PUSH = 1 << 1;
+		/// The captured pawn isn't on the target square but next to it
+		const EN_PASSANT = 1 << 2;
+		/// The king moves two squares and the rook jumps over it
+		const CASTLE = 1 << 3;
+	}
+}
+
+impl Default for MoveFlags {
+	fn default() -> Self {
+		Self::empty()
+	}
+}
+
+/// A single move of a piece, with everything needed to play it on a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+	from: Square,
+	to: Square,
+	promotion: Option<PieceType>,
+	flags: MoveFlags,
+}
+
+impl Move {
+	pub fn new(from: Square, to: Square, promotion: Option<PieceType>, flags: MoveFlags) -> Self {
+		Self {
+			from,
+			to,
+			promotion,
+			flags,
+		}
+	}
+
+	pub fn get_from(&self) -> Square {
+		self.from
+	}
+
+	pub fn get_to(&self) -> Square {
+		self.to
+	}
+
+	pub fn get_promotion(&self) -> Option<PieceType> {
+		self.promotion
+	}
+
+	pub fn get_flags(&self) -> MoveFlags {
+		self.flags
+	}
+
+	pub fn is_capture(&self) -> bool {
+		self.flags.contains(MoveFlags::CAPTURE)
+	}
+
+	pub fn is_en_passant(&self) -> bool {
+		self.flags.contains(MoveFlags::EN_PASSANT)
+	}
+
+	pub fn is_castle(&self) -> bool {
+		self.flags.contains(MoveFlags::CASTLE)
+	}
+}
+
+/// Writes the move in long algebraic notation, e.g. "e2e4" or "e7e8q"
+impl Display for Move {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}{}", self.from, self.to)?;
+		if let Some(promotion) = self.promotion {
+			write!(f, "{}", char::from(promotion).to_ascii_lowercase())?;
+		}
+		Ok(())
+	}
+}
+
+/// Why a move asked for by the frontend can't be played
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum MoveError {
+	/// Squares are numbered from 0 (a8) to 63 (h1)
+	OutOfRange(u8),
+	NoPiece(Square),
+	/// The piece belongs to the side that isn't to move
+	NotYourTurn(Square),
+	IllegalMove {
+		from: Square,
+		to: Square,
+	},
+	/// The game ended in a way no further move can change, see [`EndReason::is_mandatory`](crate::game::EndReason::is_mandatory)
+	GameOver(Outcome),
+}
+
+impl Display for MoveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MoveError::OutOfRange(i) => write!(f, "square index {i} is not between 0 and 63"),
+			MoveError::NoPiece(sq) => write!(f, "there is no piece on {sq}"),
+			MoveError::NotYourTurn(sq) => {
+				write!(f, "the piece on {sq} can't move, it's not its turn")
+			}
+			MoveError::IllegalMove { from, to } => write!(f, "{from}{to} is not a legal move"),
+			MoveError::GameOver(outcome) => write!(f, "the game is over by {:?}", outcome.reason),
+		}
+	}
+}
+
+impl std::error::Error for MoveError {}
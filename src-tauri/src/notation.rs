@@ -1,12 +1,15 @@
 use bitflags::bitflags;
+use serde::Serialize;
 
 use crate::{
+	bitboard::BitBoard,
 	board::Board,
 	piece::{ChessCell, Color, Moved, PieceType},
+	square::{File, Rank, Square},
 };
 
 bitflags! {
-	#[derive(Debug, Clone, Copy)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 	pub struct SpecialMove: u8 {
 		const CAPTURE = 1;
 		const PROMOTE_KNIGHT = 1 << 1;
@@ -16,42 +19,67 @@ bitflags! {
 	}
 }
 
+impl SpecialMove {
+	/// The flag for promoting into `piece`, empty if the piece can't be promoted into
+	pub fn from_promotion(piece: PieceType) -> Self {
+		match piece {
+			PieceType::Knight => Self::PROMOTE_KNIGHT,
+			PieceType::Bishop => Self::PROMOTE_BISHOP,
+			PieceType::Rook => Self::PROMOTE_ROOK,
+			PieceType::Queen => Self::PROMOTE_QUEEN,
+			_ => Self::empty(),
+		}
+	}
+
+	pub fn promotion(&self) -> Option<PieceType> {
+		if self.contains(Self::PROMOTE_QUEEN) {
+			Some(PieceType::Queen)
+		} else if self.contains(Self::PROMOTE_ROOK) {
+			Some(PieceType::Rook)
+		} else if self.contains(Self::PROMOTE_BISHOP) {
+			Some(PieceType::Bishop)
+		} else if self.contains(Self::PROMOTE_KNIGHT) {
+			Some(PieceType::Knight)
+		} else {
+			None
+		}
+	}
+}
+
 impl Default for SpecialMove {
 	fn default() -> Self {
 		Self::empty()
 	}
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct MoveData {
-	pub x: Option<char>,
-	pub y: Option<u8>,
+	pub file: Option<File>,
+	pub rank: Option<Rank>,
 	pub piece: Option<PieceType>,
 	pub special: SpecialMove,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum GameResult {
 	White,
 	Black,
 	Draw,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Move {
-	Move(MoveData, u8),
-	Castle(MoveData, u8),
-	Check(MoveData, u8),
-	Checkmate(MoveData, u8),
+	Move(MoveData, Square),
+	Castle(MoveData, Square),
+	Check(MoveData, Square),
+	Checkmate(MoveData, Square),
 	End(GameResult),
 }
 
-fn get_index_from_chars(chars: [char; 2]) -> Option<u8> {
-	let x = chars[0];
-	let y = chars[1].to_digit(9)? as u8;
-
-	let index = Board::notation_to_index(x, y)?;
-	Some(index)
+fn get_index_from_chars(chars: [char; 2]) -> Option<Square> {
+	let file = File::from_char(chars[0])?;
+	let rank = Rank::from_char(chars[1])?;
+	Some(Square::from_coords(file, rank))
 }
 
 pub fn start_notation(notation: &str, col: Color) -> Option<Board> {
@@ -76,39 +104,41 @@ pub fn start_notation(notation: &str, col: Color) -> Option<Board> {
 }
 
 pub fn parse_notation(notation: &str, color: Color) -> Option<Move> {
-	let mut notation = notation.to_string();
-	// converts our String into either a [char; 2] or a Vec<char>
-	// if its a [char; 2] we know it can only be a pawn moving and doing nothing else
-	let mut chars = match notation.chars().collect::<Vec<char>>().try_into() {
-		Ok(notation) => return parse_simple_pawn(notation),
-		Err(v) => v,
-	};
+	// annotations like "!?" don't change the move itself
+	let notation = notation.trim().trim_end_matches(['!', '?']);
+
+	// end result check
+	match notation {
+		"1-0" => return Some(Move::End(GameResult::White)),
+		"0-1" => return Some(Move::End(GameResult::Black)),
+		"1/2-1/2" | "½-½" => return Some(Move::End(GameResult::Draw)),
+		_ => {}
+	}
+
+	let is_mate = notation.ends_with('#');
+	let is_check = !is_mate && notation.ends_with('+');
+	let notation = notation.trim_end_matches(['+', '#']);
 
-	let castle_check = notation.replace("o", "0");
+	let castle_check = notation.replace(['o', 'O'], "0");
 	{
-		let start_row: u8 = match color {
-			Color::Black => 8,
-			Color::White => 1,
+		let start_rank = match color {
+			Color::Black => Rank::Eight,
+			Color::White => Rank::One,
 		};
 
-		if castle_check == "0-0" {
-			let index = Board::notation_to_index('g', start_row).unwrap();
+		let target_file = match castle_check.as_str() {
+			"0-0" => Some(File::G),
+			"0-0-0" => Some(File::C),
+			_ => None,
+		};
+
+		if let Some(target_file) = target_file {
+			let index = Square::from_coords(target_file, start_rank);
 
 			return Some(Move::Castle(
 				MoveData {
-					x: Some('e'),
-					y: Some(start_row),
-					piece: Some(PieceType::King),
-					special: SpecialMove::empty(),
-				},
-				index,
-			));
-		} else if castle_check == "0-0-0" {
-			let index = Board::notation_to_index('c', start_row).unwrap();
-			return Some(Move::Castle(
-				MoveData {
-					x: Some('e'),
-					y: Some(start_row),
+					file: Some(File::E),
+					rank: Some(start_rank),
 					piece: Some(PieceType::King),
 					special: SpecialMove::empty(),
 				},
@@ -117,108 +147,76 @@ pub fn parse_notation(notation: &str, color: Color) -> Option<Move> {
 		}
 	}
 
-	// end result check
-	if notation.contains('-') {
-		// 1-0; 0-1 indicates that someone won. The only other time a '-' occurs in the notation is castling which was checked before
-		let half_count = chars.iter().filter(|c| **c == '½').count();
-		if half_count == 2 {
-			return Some(Move::End(GameResult::Draw));
+	// if a pawn gets promoted, the piece type it becomes can be wrapped in brackets, since this is optional, remove brackets and continue
+	let mut chars: Vec<char> = notation
+		.chars()
+		.filter(|c| *c != '(' && *c != ')')
+		.collect();
+
+	let mut special: SpecialMove = SpecialMove::empty();
+	if let Some(last) = chars.last()
+		&& last.is_ascii_uppercase()
+	{
+		let promotion = SpecialMove::from_promotion(PieceType::try_from(*last).ok()?);
+		if promotion.is_empty() {
+			return None;
 		}
+		special.insert(promotion);
 
-		if chars[0] == '1' || chars[0] == '½' || chars[0] == '+' {
-			return Some(Move::End(GameResult::White));
-		} else {
-			return Some(Move::End(GameResult::Black));
+		chars.pop();
+		if chars.last() == Some(&'=') {
+			chars.pop();
 		}
 	}
 
-	chars = match chars.try_into() {
-		Ok(notation) => return parse_simple_piece(notation),
-		Err(v) => v,
+	// only capital letters are pieces, a lowercase 'b' is the b-file
+	let ptype = match chars.first() {
+		Some(c) if c.is_ascii_uppercase() => {
+			let ptype = PieceType::try_from(*c).ok()?;
+			chars.remove(0);
+			ptype
+		}
+		_ => PieceType::Pawn,
 	};
 
-	let ptype = PieceType::try_from(chars[0]).ok()?;
+	if ptype != PieceType::Pawn && !special.is_empty() {
+		return None;
+	}
 
-	let mut special: SpecialMove = SpecialMove::empty();
-	if notation.contains('x') {
+	if chars.contains(&'x') || chars.contains(&':') {
 		special.set(SpecialMove::CAPTURE, true);
-	};
+		chars.retain(|c| *c != 'x' && *c != ':');
+	}
 
-	// if a pawn gets promoted, the piece type it becomes can be wrapped in brackets, since this is optional, remove brackets and continue
-	if notation.contains(')') {
-		notation.remove_matches(')');
-		notation.remove_matches('(');
-		chars = notation.chars().collect();
+	if chars.len() < 2 || chars.len() > 4 {
+		return None;
 	}
 
-	let last = *chars.last().unwrap();
-	if let Ok(p) = PieceType::try_from(last) {
-		match p {
-			PieceType::Knight => special.set(SpecialMove::PROMOTE_KNIGHT, true),
-			PieceType::Bishop => special.set(SpecialMove::PROMOTE_BISHOP, true),
-			PieceType::Rook => special.set(SpecialMove::PROMOTE_ROOK, true),
-			PieceType::Queen => special.set(SpecialMove::PROMOTE_QUEEN, true),
-			_ => return None,
-		}
-	};
+	let target = chars.split_off(chars.len() - 2);
+	let index = get_index_from_chars([target[0], target[1]])?;
 
 	let mut data = MoveData {
-		x: None,
-		y: None,
+		file: None,
+		rank: None,
 		piece: Some(ptype),
 		special,
 	};
 
-	let mut index = u8::MAX;
-
-	let is_check = notation.contains('+');
-	let is_mate = notation.contains('#');
-
-	let mut temp_x: Option<char> = None;
-	let mut temp_y: Option<u8> = None;
-	for char in chars.iter().skip(1) {
-		match char.to_digit(9) {
-			Some(y) => {
-				if let Some(temp_y_data) = temp_y {
-					if data.y.is_none() {
-						data.y = Some(temp_y_data);
-						temp_y = Some(y as u8);
-					} else {
-						return None;
-					}
-				} else {
-					temp_y = Some(y as u8)
-				}
-			}
-			None => {
-				if Board::notation_to_x(*char).is_some()
-					&& let Some(temp_x_data) = temp_x
-				{
-					if data.x.is_none() {
-						data.x = Some(temp_x_data);
-						temp_x = Some(*char);
-					} else {
-						return None;
-					}
-				} else {
-					temp_x = Some(*char);
-				}
-				// we ignore other illegal characters, those might be actually garbage, but we also have stuff like 'x' for capturing
-			}
+	// everything in front of the target square disambiguates between pieces that could reach it
+	for c in chars {
+		if let Some(file) = File::from_char(c)
+			&& data.file.is_none()
+		{
+			data.file = Some(file);
+		} else if let Some(rank) = Rank::from_char(c)
+			&& data.rank.is_none()
+		{
+			data.rank = Some(rank);
+		} else {
+			return None;
 		}
 	}
 
-	if let Some(x) = temp_x
-		&& let Some(y) = temp_y
-	{
-		// we need both an x and a y so that the move done has a valid target
-		index = Board::notation_to_index(x, y)?;
-	}
-
-	if index == u8::MAX {
-		return None;
-	}
-
 	if is_check {
 		Some(Move::Check(data, index))
 	} else if is_mate {
@@ -228,29 +226,242 @@ pub fn parse_notation(notation: &str, color: Color) -> Option<Move> {
 	}
 }
 
-fn parse_simple_pawn(notation: [char; 2]) -> Option<Move> {
-	let index = get_index_from_chars(notation)?;
-	Some(Move::Move(
-		MoveData {
-			x: None,
-			y: None,
-			piece: Some(PieceType::Pawn),
-			special: SpecialMove::empty(),
-		},
-		index,
-	))
+/// Returns every square from which a piece of the side to move, matching `data`, could legally move to `target`.
+/// A single square means the move is unambiguous
+pub fn find_origins(board: &Board, data: &MoveData, target: Square) -> BitBoard {
+	let mut origins = BitBoard::new();
+
+	for i in board.color_occupancy(board.get_side_to_move()) {
+		let cell = board[i];
+
+		if let Some(piece) = data.piece
+			&& cell.get_type() != Some(piece)
+		{
+			continue;
+		}
+
+		if data.file.is_some_and(|file| file != i.file()) {
+			continue;
+		}
+
+		if data.rank.is_some_and(|rank| rank != i.rank()) {
+			continue;
+		}
+
+		if board.get_legal_moves(i).contains(target) {
+			origins.insert(i);
+		}
+	}
+
+	origins
+}
+
+/// Plays a move given as origin, target and optional promotion on `board`
+pub(crate) fn apply_move(
+	board: &mut Board,
+	from: Square,
+	to: Square,
+	promotion: Option<PieceType>,
+) {
+	board.make_move(board.create_move(from, to, promotion));
 }
 
-fn parse_simple_piece(notation: [char; 3]) -> Option<Move> {
-	let ptype = PieceType::try_from(notation[0]).ok()?;
-	let index = get_index_from_chars([notation[1], notation[2]])?;
-	Some(Move::Move(
-		MoveData {
-			x: None,
-			y: None,
-			piece: Some(ptype),
-			special: SpecialMove::empty(),
-		},
-		index,
-	))
+/// Generates the standard algebraic notation for moving the piece on `from` to `to` in `board`.
+/// The move is expected to be legal, the check and mate suffixes are derived from the resulting position
+pub fn to_san(board: &Board, from: Square, to: Square, promotion: Option<PieceType>) -> String {
+	let piece = board[from];
+	let Some(ptype) = piece.get_type() else {
+		return String::new();
+	};
+
+	let mut san = String::new();
+
+	if ptype == PieceType::King && from.file().index().abs_diff(to.file().index()) == 2 {
+		san.push_str(if to.file() == File::G { "O-O" } else { "O-O-O" });
+	} else {
+		let is_capture = board[to].get_type().is_some()
+			|| (ptype == PieceType::Pawn && from.file() != to.file());
+
+		if ptype == PieceType::Pawn {
+			if is_capture {
+				san.push(from.file().to_char());
+			}
+		} else {
+			san.push(char::from(ptype));
+
+			// other pieces of the same kind that could go to the same square
+			let data = MoveData {
+				piece: Some(ptype),
+				..Default::default()
+			};
+			let mut others = find_origins(board, &data, to);
+			others.remove(from);
+
+			if !others.is_empty() {
+				let same_file = others.iter().any(|i| i.file() == from.file());
+				let same_rank = others.iter().any(|i| i.rank() == from.rank());
+
+				if !same_file {
+					san.push(from.file().to_char());
+				} else if !same_rank {
+					san.push(from.rank().to_char());
+				} else {
+					san.push(from.file().to_char());
+					san.push(from.rank().to_char());
+				}
+			}
+		}
+
+		if is_capture {
+			san.push('x');
+		}
+		san.push_str(&to.to_string());
+
+		if let Some(promotion) = promotion {
+			san.push('=');
+			san.push(char::from(promotion));
+		}
+	}
+
+	let mut after = board.clone();
+	apply_move(&mut after, from, to, promotion);
+	if after.is_in_check(after.get_side_to_move()) {
+		san.push(if after.has_legal_moves() { '+' } else { '#' });
+	}
+
+	san
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		fen::START_FEN,
+		notation::{GameResult, Move, MoveData, SpecialMove, find_origins, parse_notation, to_san},
+		piece::{Color, PieceType},
+		square::{File, Rank, Square},
+	};
+
+	#[test]
+	fn test_parse_notation() {
+		assert_eq!(
+			parse_notation("e4", Color::White),
+			Some(Move::Move(
+				MoveData {
+					piece: Some(PieceType::Pawn),
+					..Default::default()
+				},
+				Square::E4
+			))
+		);
+		assert_eq!(
+			parse_notation("bxc3", Color::Black),
+			Some(Move::Move(
+				MoveData {
+					file: Some(File::B),
+					piece: Some(PieceType::Pawn),
+					special: SpecialMove::CAPTURE,
+					..Default::default()
+				},
+				Square::C3
+			))
+		);
+		assert_eq!(
+			parse_notation("Nbxd7+", Color::White),
+			Some(Move::Check(
+				MoveData {
+					file: Some(File::B),
+					piece: Some(PieceType::Knight),
+					special: SpecialMove::CAPTURE,
+					..Default::default()
+				},
+				Square::D7
+			))
+		);
+		assert_eq!(
+			parse_notation("R1e2", Color::White),
+			Some(Move::Move(
+				MoveData {
+					rank: Some(Rank::One),
+					piece: Some(PieceType::Rook),
+					..Default::default()
+				},
+				Square::E2
+			))
+		);
+		assert_eq!(
+			parse_notation("exd8=Q#", Color::White),
+			Some(Move::Checkmate(
+				MoveData {
+					file: Some(File::E),
+					piece: Some(PieceType::Pawn),
+					special: SpecialMove::CAPTURE | SpecialMove::PROMOTE_QUEEN,
+					..Default::default()
+				},
+				Square::D8
+			))
+		);
+		assert!(matches!(
+			parse_notation("O-O-O", Color::Black),
+			Some(Move::Castle(_, i)) if i == Square::C8
+		));
+		assert_eq!(
+			parse_notation("1/2-1/2", Color::White),
+			Some(Move::End(GameResult::Draw))
+		);
+		assert_eq!(parse_notation("Nf9", Color::White), None);
+		assert_eq!(parse_notation("Ke8=Q", Color::White), None);
+	}
+
+	#[test]
+	fn test_find_origins() {
+		let board = Board::from_fen("4k3/8/8/8/8/2N3N1/8/4K3 w - - 0 1").unwrap();
+		let data = MoveData {
+			piece: Some(PieceType::Knight),
+			..Default::default()
+		};
+		assert_eq!(find_origins(&board, &data, Square::E4).size(), 2);
+
+		let data = MoveData {
+			file: Some(File::G),
+			..data
+		};
+		let origins = find_origins(&board, &data, Square::E4);
+		assert_eq!(origins.iter().collect::<Vec<Square>>(), vec![Square::G3]);
+
+		let start = Board::from_fen(START_FEN).unwrap();
+		let data = MoveData {
+			piece: Some(PieceType::Bishop),
+			..Default::default()
+		};
+		assert!(find_origins(&start, &data, Square::E3).is_empty());
+	}
+
+	#[test]
+	fn test_to_san() {
+		let board = Board::from_fen("4k3/8/8/8/8/2N3N1/8/4K3 w - - 0 1").unwrap();
+		assert_eq!(to_san(&board, Square::C3, Square::E4, None), "Nce4");
+		assert_eq!(to_san(&board, Square::C3, Square::D5, None), "Nd5");
+
+		let board = Board::from_fen("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1").unwrap();
+		assert_eq!(to_san(&board, Square::A1, Square::A2, None), "R1a2");
+
+		let board = Board::from_fen("8/7k/8/8/1Q1Q4/8/1Q6/7K w - - 0 1").unwrap();
+		assert_eq!(to_san(&board, Square::B4, Square::C3, None), "Qb4c3");
+
+		let board = Board::from_fen("3rk3/2P5/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+		assert_eq!(
+			to_san(&board, Square::C7, Square::D8, Some(PieceType::Knight)),
+			"cxd8=N"
+		);
+		assert_eq!(
+			to_san(&board, Square::C7, Square::D8, Some(PieceType::Queen)),
+			"cxd8=Q+"
+		);
+
+		let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+		assert_eq!(to_san(&board, Square::A1, Square::A8, None), "Ra8#");
+		assert_eq!(to_san(&board, Square::E1, Square::G1, None), "O-O");
+		assert_eq!(to_san(&board, Square::E1, Square::C1, None), "O-O-O");
+	}
 }
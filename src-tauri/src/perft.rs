@@ -0,0 +1,119 @@
+use crate::{board::Board, moves::Move};
+
+impl Board {
+	/// Counts the leaf nodes of the legal move tree `depth` plies deep, the standard way to check a move generator against known results
+	pub fn perft(&mut self, depth: u32) -> u64 {
+		if depth == 0 {
+			return 1;
+		}
+
+		let moves = self.get_all_legal_moves();
+		if depth == 1 {
+			return moves.len() as u64;
+		}
+
+		let mut nodes = 0;
+		for mv in moves {
+			let undo = self.make_move(mv);
+			nodes += self.perft(depth - 1);
+			self.unmake_move(undo);
+		}
+		nodes
+	}
+
+	/// [`Board::perft`] split up by the first move, to narrow down where a generator goes wrong
+	pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+		if depth == 0 {
+			return Vec::new();
+		}
+
+		self.get_all_legal_moves()
+			.into_iter()
+			.map(|mv| {
+				let undo = self.make_move(mv);
+				let nodes = self.perft(depth - 1);
+				self.unmake_move(undo);
+				(mv, nodes)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{board::Board, fen::START_FEN};
+
+	// https://www.chessprogramming.org/Perft_Results
+	const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+	const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+	const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+	const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+	const POSITION_6: &str =
+		"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+	fn check(fen: &str, expected: &[u64]) {
+		let mut board = Board::from_fen(fen).unwrap();
+		for (depth, &nodes) in expected.iter().enumerate() {
+			let depth = depth as u32 + 1;
+			assert_eq!(board.perft(depth), nodes, "{fen} at depth {depth}");
+		}
+		// every move has to be undone perfectly for the counts to mean anything
+		assert_eq!(board, Board::from_fen(fen).unwrap());
+	}
+
+	#[test]
+	fn test_start_position() {
+		check(START_FEN, &[20, 400, 8_902]);
+	}
+
+	#[test]
+	fn test_kiwipete() {
+		check(KIWIPETE, &[48, 2_039]);
+	}
+
+	#[test]
+	fn test_position_3() {
+		check(POSITION_3, &[14, 191, 2_812]);
+	}
+
+	#[test]
+	fn test_position_4() {
+		check(POSITION_4, &[6, 264, 9_467]);
+	}
+
+	#[test]
+	fn test_position_5() {
+		check(POSITION_5, &[44, 1_486]);
+	}
+
+	#[test]
+	fn test_position_6() {
+		check(POSITION_6, &[46, 2_079]);
+	}
+
+	/// The deeper counts take a while, run them with `cargo test --release -- --ignored`
+	#[test]
+	#[ignore]
+	fn test_deep() {
+		check(START_FEN, &[20, 400, 8_902, 197_281, 4_865_609]);
+		check(KIWIPETE, &[48, 2_039, 97_862, 4_085_603]);
+		check(POSITION_3, &[14, 191, 2_812, 43_238, 674_624]);
+		check(POSITION_4, &[6, 264, 9_467, 422_333]);
+		check(POSITION_5, &[44, 1_486, 62_379, 2_103_487]);
+		check(POSITION_6, &[46, 2_079, 89_890, 3_894_594]);
+	}
+
+	#[test]
+	fn test_divide() {
+		let mut board = Board::new();
+		let divide = board.divide(3);
+		assert_eq!(divide.len(), 20);
+		assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8_902);
+
+		let e4 = divide
+			.iter()
+			.find(|(mv, _)| mv.to_string() == "e2e4")
+			.unwrap();
+		assert_eq!(e4.1, 600);
+	}
+}
@@ -0,0 +1,770 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use crate::{
+	board::Board,
+	fen::{FenError, START_FEN},
+	notation::{self, GameResult, Move, MoveData},
+	piece::{Color, PieceType},
+	square::Square,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+	UnterminatedTag,
+	UnterminatedComment,
+	/// A ')' without a matching '(', or a variation that is still open at the end of the game
+	UnbalancedVariation,
+	/// A variation has to follow the move it replaces
+	MisplacedVariation,
+	/// The token isn't valid SAN
+	InvalidMove(String),
+	/// No piece can make the move in the current position
+	IllegalMove(String),
+	/// More than one piece can make the move and the SAN doesn't say which
+	AmbiguousMove(String),
+	Fen(FenError),
+}
+
+impl Display for PgnError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			PgnError::UnterminatedTag => write!(f, "unterminated tag pair"),
+			PgnError::UnterminatedComment => write!(f, "unterminated comment"),
+			PgnError::UnbalancedVariation => write!(f, "unbalanced variation"),
+			PgnError::MisplacedVariation => write!(f, "variation without a preceding move"),
+			PgnError::InvalidMove(san) => write!(f, "\"{san}\" is not a valid move"),
+			PgnError::IllegalMove(san) => write!(f, "\"{san}\" is not legal in this position"),
+			PgnError::AmbiguousMove(san) => write!(f, "\"{san}\" is ambiguous"),
+			PgnError::Fen(e) => write!(f, "invalid FEN tag: {e}"),
+		}
+	}
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+	fn from(value: FenError) -> Self {
+		PgnError::Fen(value)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnMove {
+	/// The move as it was written in the movetext
+	pub san: String,
+	pub from: Square,
+	pub to: Square,
+	pub promotion: Option<PieceType>,
+	/// Numeric annotation glyphs, suffixes like "!?" are converted into their NAG
+	pub nags: Vec<u8>,
+	/// Comments following the move
+	pub comments: Vec<String>,
+	/// Alternatives to this move, each starting from the position before it
+	pub variations: Vec<Vec<PgnMove>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+	/// Tag pairs in the order they appear in
+	pub tags: Vec<(String, String)>,
+	/// Either the standard starting position or the one given by the FEN tag
+	pub start: Board,
+	/// Comments in front of the first move
+	pub comments: Vec<String>,
+	pub moves: Vec<PgnMove>,
+	/// None if the game is still ongoing ("*")
+	pub result: Option<GameResult>,
+}
+
+impl PgnGame {
+	pub fn new(start: Board) -> Self {
+		Self {
+			tags: Vec::new(),
+			start,
+			comments: Vec::new(),
+			moves: Vec::new(),
+			result: None,
+		}
+	}
+
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags
+			.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, v)| v.as_str())
+	}
+
+	pub fn set_tag(&mut self, name: &str, value: &str) {
+		match self.tags.iter_mut().find(|(n, _)| n == name) {
+			Some((_, v)) => *v = value.to_string(),
+			None => self.tags.push((name.to_string(), value.to_string())),
+		}
+	}
+
+	/// The position after every move of the main line was played
+	pub fn final_position(&self) -> Board {
+		let mut board = self.start.clone();
+		for mv in &self.moves {
+			apply_move(&mut board, mv);
+		}
+		board
+	}
+
+	/// Appends a move to the main line. The move is expected to be legal in the final position
+	pub fn push_move(&mut self, from: Square, to: Square, promotion: Option<PieceType>) {
+		let board = self.final_position();
+		self.moves.push(PgnMove {
+			san: notation::to_san(&board, from, to, promotion),
+			from,
+			to,
+			promotion,
+			nags: Vec::new(),
+			comments: Vec::new(),
+			variations: Vec::new(),
+		});
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	Tag(String, String),
+	Comment(String),
+	Nag(u8),
+	VariationStart,
+	VariationEnd,
+	San(String),
+	/// None for "*"
+	Result(Option<GameResult>),
+}
+
+struct Tokenizer<'a> {
+	chars: Peekable<Chars<'a>>,
+	at_line_start: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+	fn new(pgn: &'a str) -> Self {
+		Self {
+			chars: pgn.chars().peekable(),
+			at_line_start: true,
+		}
+	}
+
+	fn next_char(&mut self) -> Option<char> {
+		let c = self.chars.next()?;
+		self.at_line_start = c == '\n';
+		Some(c)
+	}
+
+	fn skip_line(&mut self) {
+		while let Some(c) = self.next_char() {
+			if c == '\n' {
+				break;
+			}
+		}
+	}
+
+	fn read_tag(&mut self) -> Result<Token, PgnError> {
+		let mut name = String::new();
+		while let Some(c) = self.chars.peek()
+			&& !c.is_whitespace()
+			&& *c != '"'
+		{
+			name.push(*c);
+			self.next_char();
+		}
+
+		while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+			self.next_char();
+		}
+
+		if self.next_char() != Some('"') {
+			return Err(PgnError::UnterminatedTag);
+		}
+
+		let mut value = String::new();
+		loop {
+			match self.next_char() {
+				Some('\\') => value.push(self.next_char().ok_or(PgnError::UnterminatedTag)?),
+				Some('"') => break,
+				Some(c) => value.push(c),
+				None => return Err(PgnError::UnterminatedTag),
+			}
+		}
+
+		loop {
+			match self.next_char() {
+				Some(']') => return Ok(Token::Tag(name, value)),
+				Some(c) if c.is_whitespace() => {}
+				_ => return Err(PgnError::UnterminatedTag),
+			}
+		}
+	}
+
+	/// Reads a symbol and splits it into move number, move and suffix annotation.
+	/// Returns every token it contained, since e.g. "12.Nf3!" is a single word
+	fn read_symbol(&mut self, first: char) -> Vec<Token> {
+		let mut symbol = String::from(first);
+		while let Some(c) = self.chars.peek()
+			&& !c.is_whitespace()
+			&& !"{}()[];$".contains(*c)
+		{
+			symbol.push(*c);
+			self.next_char();
+		}
+
+		match symbol.as_str() {
+			"1-0" => return vec![Token::Result(Some(GameResult::White))],
+			"0-1" => return vec![Token::Result(Some(GameResult::Black))],
+			"1/2-1/2" | "½-½" => return vec![Token::Result(Some(GameResult::Draw))],
+			"*" => return vec![Token::Result(None)],
+			_ => {}
+		}
+
+		// move numbers like "12." or "12..." are only there for readability
+		let without_number = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+		let san = if without_number.starts_with('.') {
+			without_number.trim_start_matches('.')
+		} else if without_number.is_empty() {
+			""
+		} else {
+			&symbol
+		};
+
+		let mut tokens = Vec::new();
+		if san.is_empty() {
+			return tokens;
+		}
+
+		let move_part = san.trim_end_matches(['!', '?']);
+		tokens.push(Token::San(move_part.to_string()));
+
+		let nag = match &san[move_part.len()..] {
+			"!" => Some(1),
+			"?" => Some(2),
+			"!!" => Some(3),
+			"??" => Some(4),
+			"!?" => Some(5),
+			"?!" => Some(6),
+			_ => None,
+		};
+		if let Some(nag) = nag {
+			tokens.push(Token::Nag(nag));
+		}
+
+		tokens
+	}
+
+	fn tokenize(mut self) -> Result<Vec<Token>, PgnError> {
+		let mut tokens = Vec::new();
+
+		while let Some(c) = {
+			// '%' escapes the rest of the line, but only at its very beginning
+			if self.at_line_start && self.chars.peek() == Some(&'%') {
+				self.skip_line();
+			}
+			self.next_char()
+		} {
+			match c {
+				c if c.is_whitespace() => {}
+				'[' => tokens.push(self.read_tag()?),
+				'{' => {
+					let mut comment = String::new();
+					loop {
+						match self.next_char() {
+							Some('}') => break,
+							Some(c) => comment.push(c),
+							None => return Err(PgnError::UnterminatedComment),
+						}
+					}
+					tokens.push(Token::Comment(comment.trim().to_string()));
+				}
+				';' => {
+					let mut comment = String::new();
+					while let Some(c) = self.next_char()
+						&& c != '\n'
+					{
+						comment.push(c);
+					}
+					tokens.push(Token::Comment(comment.trim().to_string()));
+				}
+				'(' => tokens.push(Token::VariationStart),
+				')' => tokens.push(Token::VariationEnd),
+				'$' => {
+					let mut nag = String::new();
+					while let Some(c) = self.chars.peek()
+						&& c.is_ascii_digit()
+					{
+						nag.push(*c);
+						self.next_char();
+					}
+					let nag = nag
+						.parse()
+						.map_err(|_| PgnError::InvalidMove(format!("${nag}")))?;
+					tokens.push(Token::Nag(nag));
+				}
+				c => tokens.extend(self.read_symbol(c)),
+			}
+		}
+
+		Ok(tokens)
+	}
+}
+
+/// Resolves a SAN move into the concrete move it describes in `board`
+fn resolve_san(board: &Board, san: &str) -> Result<PgnMove, PgnError> {
+	let parsed = notation::parse_notation(san, board.get_side_to_move())
+		.ok_or_else(|| PgnError::InvalidMove(san.to_string()))?;
+
+	let (data, target): (MoveData, Square) = match parsed {
+		Move::Move(data, target)
+		| Move::Castle(data, target)
+		| Move::Check(data, target)
+		| Move::Checkmate(data, target) => (data, target),
+		Move::End(_) => return Err(PgnError::InvalidMove(san.to_string())),
+	};
+
+	let origins = notation::find_origins(board, &data, target);
+	let from = match origins.size() {
+		0 => return Err(PgnError::IllegalMove(san.to_string())),
+		1 => origins.iter().next().unwrap(),
+		_ => return Err(PgnError::AmbiguousMove(san.to_string())),
+	};
+
+	// a pawn reaching the last rank has to say what it becomes, and nothing else may promote
+	let promotion = data.special.promotion();
+	if board.is_promotion(from, target) != promotion.is_some() {
+		return Err(PgnError::IllegalMove(san.to_string()));
+	}
+
+	Ok(PgnMove {
+		san: san.to_string(),
+		from,
+		to: target,
+		promotion,
+		nags: Vec::new(),
+		comments: Vec::new(),
+		variations: Vec::new(),
+	})
+}
+
+fn apply_move(board: &mut Board, mv: &PgnMove) {
+	notation::apply_move(board, mv.from, mv.to, mv.promotion);
+}
+
+/// Parses one line of moves, recursing into variations. Stops at the end of the line or the game
+fn parse_line(
+	tokens: &mut Peekable<std::vec::IntoIter<Token>>,
+	mut board: Board,
+	leading_comments: &mut Vec<String>,
+) -> Result<Vec<PgnMove>, PgnError> {
+	let mut moves: Vec<PgnMove> = Vec::new();
+	// variations start from the position before the last move
+	let mut previous = board.clone();
+
+	while let Some(token) = tokens.peek() {
+		match token {
+			Token::Tag(_, _) | Token::Result(_) | Token::VariationEnd => break,
+			_ => {}
+		}
+
+		match tokens.next().unwrap() {
+			Token::Comment(comment) => match moves.last_mut() {
+				Some(last) => last.comments.push(comment),
+				None => leading_comments.push(comment),
+			},
+			Token::Nag(nag) => {
+				if let Some(last) = moves.last_mut() {
+					last.nags.push(nag);
+				}
+			}
+			Token::VariationStart => {
+				if moves.is_empty() {
+					return Err(PgnError::MisplacedVariation);
+				}
+
+				let mut variation_comments = Vec::new();
+				let mut variation = parse_line(tokens, previous.clone(), &mut variation_comments)?;
+				if tokens.next() != Some(Token::VariationEnd) {
+					return Err(PgnError::UnbalancedVariation);
+				}
+
+				// comments in front of the variation's first move belong to that move
+				if let Some(first) = variation.first_mut() {
+					variation_comments.append(&mut first.comments);
+					first.comments = variation_comments;
+				}
+				moves.last_mut().unwrap().variations.push(variation);
+			}
+			Token::San(san) => {
+				let mv = resolve_san(&board, &san)?;
+				previous = board.clone();
+				apply_move(&mut board, &mv);
+				moves.push(mv);
+			}
+			Token::Tag(_, _) | Token::Result(_) | Token::VariationEnd => unreachable!(),
+		}
+	}
+
+	Ok(moves)
+}
+
+/// Reads every game of a PGN file.
+/// SAN moves are resolved against the position, so illegal or ambiguous moves are reported as errors
+pub fn read_pgn(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+	let mut tokens = Tokenizer::new(pgn).tokenize()?.into_iter().peekable();
+	let mut games = Vec::new();
+
+	while tokens.peek().is_some() {
+		let mut tags = Vec::new();
+		while let Some(Token::Tag(_, _)) = tokens.peek() {
+			if let Some(Token::Tag(name, value)) = tokens.next() {
+				tags.push((name, value));
+			}
+		}
+
+		let fen = tags
+			.iter()
+			.find(|(name, _)| name == "FEN")
+			.map(|(_, fen)| fen.as_str())
+			.unwrap_or(START_FEN);
+		let start = Board::from_fen(fen)?;
+
+		let mut comments = Vec::new();
+		let moves = parse_line(&mut tokens, start.clone(), &mut comments)?;
+
+		// games without a result token are treated as ongoing
+		let result = match tokens.peek() {
+			Some(Token::Result(result)) => {
+				let result = *result;
+				tokens.next();
+				result
+			}
+			Some(Token::VariationEnd) => return Err(PgnError::UnbalancedVariation),
+			_ => None,
+		};
+
+		games.push(PgnGame {
+			tags,
+			start,
+			comments,
+			moves,
+			result,
+		});
+	}
+
+	Ok(games)
+}
+
+/// The tags every exported game starts with, together with the value used if the game doesn't have them
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+	("Event", "?"),
+	("Site", "?"),
+	("Date", "????.??.??"),
+	("Round", "?"),
+	("White", "?"),
+	("Black", "?"),
+	("Result", "*"),
+];
+
+const LINE_WIDTH: usize = 80;
+
+fn result_token(result: Option<GameResult>) -> &'static str {
+	match result {
+		Some(GameResult::White) => "1-0",
+		Some(GameResult::Black) => "0-1",
+		Some(GameResult::Draw) => "1/2-1/2",
+		None => "*",
+	}
+}
+
+fn escape_tag(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Wraps `words` in the given delimiters without a space between them and the first and last word
+fn enclose(mut words: Vec<String>, open: char, close: char) -> Vec<String> {
+	if words.is_empty() {
+		return vec![format!("{open}{close}")];
+	}
+
+	words[0].insert(0, open);
+	words.last_mut().unwrap().push(close);
+	words
+}
+
+fn write_comment(words: &mut Vec<String>, comment: &str) {
+	let comment_words = comment.split_whitespace().map(|w| w.to_string()).collect();
+	words.extend(enclose(comment_words, '{', '}'));
+}
+
+/// Writes a line of moves starting at `board`, with the SAN regenerated from the position
+fn write_line(words: &mut Vec<String>, board: &Board, moves: &[PgnMove], mut force_number: bool) {
+	let mut board = board.clone();
+
+	for mv in moves {
+		// the move number is kept on the same line as its move
+		let number = board.get_fullmove_number();
+		let san = notation::to_san(&board, mv.from, mv.to, mv.promotion);
+		words.push(match board.get_side_to_move() {
+			Color::White => format!("{number}. {san}"),
+			// black's moves only need a number when something interrupted the movetext
+			Color::Black if force_number => format!("{number}... {san}"),
+			Color::Black => san,
+		});
+		for nag in &mv.nags {
+			words.push(format!("${nag}"));
+		}
+		for comment in &mv.comments {
+			write_comment(words, comment);
+		}
+
+		for variation in &mv.variations {
+			let mut variation_words = Vec::new();
+			write_line(&mut variation_words, &board, variation, true);
+			words.extend(enclose(variation_words, '(', ')'));
+		}
+
+		force_number = !mv.comments.is_empty() || !mv.variations.is_empty();
+		apply_move(&mut board, mv);
+	}
+}
+
+/// Exports a game as PGN, starting with the Seven Tag Roster followed by the remaining tags.
+/// The movetext is wrapped at 80 characters
+pub fn write_pgn(game: &PgnGame) -> String {
+	let mut pgn = String::new();
+
+	for (name, default) in SEVEN_TAG_ROSTER {
+		let value = match name {
+			"Result" => result_token(game.result),
+			_ => game.tag(name).unwrap_or(default),
+		};
+		pgn.push_str(&format!("[{name} \"{}\"]\n", escape_tag(value)));
+	}
+
+	let fen = game.start.to_fen();
+	let custom_start = fen != START_FEN;
+	if custom_start {
+		pgn.push_str("[SetUp \"1\"]\n");
+		pgn.push_str(&format!("[FEN \"{fen}\"]\n"));
+	}
+
+	for (name, value) in &game.tags {
+		let is_roster = SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name);
+		if is_roster || name == "SetUp" || name == "FEN" {
+			continue;
+		}
+		pgn.push_str(&format!("[{name} \"{}\"]\n", escape_tag(value)));
+	}
+	pgn.push('\n');
+
+	let mut words = Vec::new();
+	for comment in &game.comments {
+		write_comment(&mut words, comment);
+	}
+	write_line(
+		&mut words,
+		&game.start,
+		&game.moves,
+		!game.comments.is_empty(),
+	);
+	words.push(result_token(game.result).to_string());
+
+	let mut line_len = 0;
+	for word in words {
+		if line_len > 0 && line_len + 1 + word.len() > LINE_WIDTH {
+			pgn.push('\n');
+			line_len = 0;
+		} else if line_len > 0 {
+			pgn.push(' ');
+			line_len += 1;
+		}
+
+		line_len += word.len();
+		pgn.push_str(&word);
+	}
+	pgn.push('\n');
+
+	pgn
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		notation::GameResult,
+		pgn::{PgnError, PgnGame, read_pgn, write_pgn},
+		piece::{Color, PieceType},
+		square::Square,
+	};
+
+	const GAME: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+"#;
+
+	#[test]
+	fn test_read_game() {
+		let games = read_pgn(GAME).unwrap();
+		assert_eq!(games.len(), 1);
+
+		let game = &games[0];
+		assert_eq!(game.tag("White"), Some("Fischer, Robert J."));
+		assert_eq!(game.tags.len(), 7);
+		assert_eq!(game.moves.len(), 85);
+		assert_eq!(game.result, Some(GameResult::Draw));
+		assert_eq!(
+			game.moves[4].comments,
+			vec!["This opening is called the Ruy Lopez.".to_string()]
+		);
+
+		let board = game.final_position();
+		assert_eq!(board.get_side_to_move(), Color::Black);
+		assert_eq!(board.get_fullmove_number(), 43);
+		let rook = board[Square::E6];
+		assert_eq!(rook.get_type(), Some(PieceType::Rook));
+		assert_eq!(rook.get_color(), Color::White);
+	}
+
+	#[test]
+	fn test_variations_and_annotations() {
+		let pgn = "{Start} 1. e4 $1 e5 (1... c5!? 2. Nf3 (2. Nc3 {closed}) 2... d6) 2. Nf3?! *";
+		let games = read_pgn(pgn).unwrap();
+		let game = &games[0];
+
+		assert_eq!(game.comments, vec!["Start".to_string()]);
+		assert_eq!(game.result, None);
+		assert_eq!(game.moves.len(), 3);
+		assert_eq!(game.moves[0].nags, vec![1]);
+		assert_eq!(game.moves[2].nags, vec![6]);
+
+		let sicilian = &game.moves[1].variations[0];
+		assert_eq!(sicilian.len(), 3);
+		assert_eq!(sicilian[0].san, "c5");
+		assert_eq!(sicilian[0].nags, vec![5]);
+		assert_eq!(sicilian[1].variations[0][0].san, "Nc3");
+		assert_eq!(
+			sicilian[1].variations[0][0].comments,
+			vec!["closed".to_string()]
+		);
+		assert_eq!(sicilian[2].to, Square::D6);
+	}
+
+	#[test]
+	fn test_multiple_games_and_fen() {
+		let pgn = r#"[Event "A"]
+1. d4 d5 1-0
+
+[Event "B"]
+[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 1"]
+1. a8=Q+ Kd7 0-1
+"#;
+		let games = read_pgn(pgn).unwrap();
+		assert_eq!(games.len(), 2);
+		assert_eq!(games[0].result, Some(GameResult::White));
+		assert_eq!(games[1].tag("Event"), Some("B"));
+		assert_eq!(games[1].moves[0].promotion, Some(PieceType::Queen));
+
+		let queen = games[1].final_position()[Square::A8];
+		assert_eq!(queen.get_type(), Some(PieceType::Queen));
+	}
+
+	#[test]
+	fn test_en_passant() {
+		let games = read_pgn("1. e4 a6 2. e5 d5 3. exd6 *").unwrap();
+		let board = games[0].final_position();
+		assert_eq!(
+			board.to_fen(),
+			"rnbqkbnr/1pp1pppp/p2P4/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3"
+		);
+		assert!(write_pgn(&games[0]).ends_with("\n1. e4 a6 2. e5 d5 3. exd6 *\n"));
+	}
+
+	#[test]
+	fn test_pgn_errors() {
+		assert_eq!(
+			read_pgn("1. e5 *").err(),
+			Some(PgnError::IllegalMove("e5".to_string()))
+		);
+		assert_eq!(
+			read_pgn("1. d4 d5 2. Nf3 e6 3. Nd2 *").err(),
+			Some(PgnError::AmbiguousMove("Nd2".to_string()))
+		);
+		assert_eq!(
+			read_pgn("[FEN \"4k3/P7/8/8/8/8/8/4K3 w - - 0 1\"]\n1. a8 *").err(),
+			Some(PgnError::IllegalMove("a8".to_string()))
+		);
+		assert_eq!(
+			read_pgn("1. e4=Q *").err(),
+			Some(PgnError::IllegalMove("e4=Q".to_string()))
+		);
+		assert_eq!(
+			read_pgn("1. e4 {open").err(),
+			Some(PgnError::UnterminatedComment)
+		);
+		assert_eq!(
+			read_pgn("1. e4 (1. d4 *").err(),
+			Some(PgnError::UnbalancedVariation)
+		);
+		assert_eq!(
+			read_pgn("1. Zz4 *").err(),
+			Some(PgnError::InvalidMove("Zz4".to_string()))
+		);
+	}
+
+	#[test]
+	fn test_write_game() {
+		let games = read_pgn(GAME).unwrap();
+		let written = write_pgn(&games[0]);
+		assert!(written.starts_with(GAME.split("\n\n").next().unwrap()));
+		assert!(written.lines().all(|line| line.len() <= 80));
+		assert!(written.contains(" 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6"));
+		assert!(written.ends_with("43. Re6 1/2-1/2\n"));
+
+		let reread = read_pgn(&written).unwrap();
+		let sans = |game: &PgnGame| game.moves.iter().map(|m| m.san.clone()).collect::<Vec<_>>();
+		assert_eq!(sans(&reread[0]), sans(&games[0]));
+		assert_eq!(reread[0].tags, games[0].tags);
+		assert_eq!(reread[0].result, games[0].result);
+	}
+
+	#[test]
+	fn test_write_variations() {
+		let pgn = "{Start} 1. e4 $1 e5 (1... c5 2. Nf3 (2. Nc3 {closed}) 2... d6) 2. Nf3 *";
+		let games = read_pgn(pgn).unwrap();
+		let written = write_pgn(&games[0]);
+
+		assert!(written.starts_with("[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]"));
+		assert!(written.ends_with(
+			"\n{Start} 1. e4 $1 e5 (1... c5 2. Nf3 (2. Nc3 {closed}) 2... d6) 2. Nf3 *\n"
+		));
+	}
+
+	#[test]
+	fn test_write_played_game() {
+		let mut game = PgnGame::new(Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+		game.set_tag("White", "Me");
+		game.push_move(Square::A7, Square::A8, Some(PieceType::Queen));
+		game.result = Some(GameResult::White);
+
+		let written = write_pgn(&game);
+		assert!(written.contains("[White \"Me\"]\n"));
+		assert!(written.contains("[FEN \"4k3/P7/8/8/8/8/8/4K3 w - - 0 1\"]\n"));
+		assert!(written.ends_with("\n1. a8=Q+ 1-0\n"));
+	}
+}
@@ -1,6 +1,6 @@
-use serde::{Serialize, ser::SerializeStruct};
+use serde::{Deserialize, Serialize, ser::SerializeStruct};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum PieceType {
 	Pawn = 0b00000001,
@@ -31,7 +31,7 @@ impl TryFrom<char> for PieceType {
 	type Error = ();
 
 	fn try_from(value: char) -> Result<Self, Self::Error> {
-		match value.to_ascii_lowercase() {
+		match value.to_ascii_uppercase() {
 			'P' => Ok(PieceType::Pawn),
 			'N' => Ok(PieceType::Knight),
 			'B' => Ok(PieceType::Bishop),
@@ -43,13 +43,35 @@ impl TryFrom<char> for PieceType {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+impl From<PieceType> for char {
+	fn from(value: PieceType) -> Self {
+		match value {
+			PieceType::Pawn => 'P',
+			PieceType::Knight => 'N',
+			PieceType::Bishop => 'B',
+			PieceType::Rook => 'R',
+			PieceType::Queen => 'Q',
+			PieceType::King => 'K',
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Color {
 	White = 0b00000000,
 	Black = 0b00001000,
 }
 
+impl Color {
+	pub fn opposite(self) -> Color {
+		match self {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		}
+	}
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[repr(u8)]
 pub enum Moved {
@@ -64,7 +86,7 @@ pub enum EnPassant {
 	Yes = 0b00100000,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct ChessCell {
 	piece: u8,
@@ -134,8 +156,8 @@ impl ChessCell {
 
 	pub fn set_en_pessant(&mut self, value: bool) {
 		match value {
-			true => self.piece = self.piece & !(EnPassant::Yes as u8),
-			false => self.piece = self.piece | (EnPassant::Yes as u8),
+			true => self.piece |= EnPassant::Yes as u8,
+			false => self.piece &= !(EnPassant::Yes as u8),
 		}
 	}
 }
@@ -202,8 +224,22 @@ mod tests {
 				let piece = ChessCell::with_piece(piece_type, col, Moved::Yes);
 				assert_eq!(piece.get_color(), col);
 				assert_eq!(piece.get_type(), Some(piece_type));
-				assert_eq!(piece.has_moved(), true);
+				assert!(piece.has_moved());
 			}
 		}
 	}
+
+	#[test]
+	fn test_en_passant_flag() {
+		let mut piece = ChessCell::with_piece(PieceType::Pawn, Color::Black, Moved::Yes);
+		assert!(!piece.eligable_en_pessant_take());
+
+		piece.set_en_pessant(true);
+		assert!(piece.eligable_en_pessant_take());
+		assert_eq!(piece.get_type(), Some(PieceType::Pawn));
+		assert_eq!(piece.get_color(), Color::Black);
+
+		piece.set_en_pessant(false);
+		assert!(!piece.eligable_en_pessant_take());
+	}
 }
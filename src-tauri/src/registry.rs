@@ -0,0 +1,156 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::Serialize;
+
+use crate::{
+	board::Board,
+	fen::FenError,
+	game::{Game, Outcome},
+	moves::MoveError,
+};
+
+/// Identifies a game in [`Games`]. IDs are never reused, so a closed game can't be mistaken for a newer one
+pub type GameId = u32;
+
+/// Everything that can go wrong in a command addressing a game by its ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum GameError {
+	UnknownGame(GameId),
+	Move(MoveError),
+	Fen(FenError),
+}
+
+impl Display for GameError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GameError::UnknownGame(id) => write!(f, "there is no game with ID {id}"),
+			GameError::Move(e) => e.fmt(f),
+			GameError::Fen(e) => e.fmt(f),
+		}
+	}
+}
+
+impl std::error::Error for GameError {}
+
+impl From<MoveError> for GameError {
+	fn from(value: MoveError) -> Self {
+		GameError::Move(value)
+	}
+}
+
+impl From<FenError> for GameError {
+	fn from(value: FenError) -> Self {
+		GameError::Fen(value)
+	}
+}
+
+/// A short description of an open game, for listing them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameInfo {
+	pub id: GameId,
+	pub fen: String,
+	/// How many moves are played on the board
+	pub ply: usize,
+	pub status: Option<Outcome>,
+}
+
+/// The open games, each independent of the others
+#[derive(Debug, Default)]
+pub struct Games {
+	games: BTreeMap<GameId, Game>,
+	next_id: GameId,
+}
+
+impl Games {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Starts a new game from `start` and returns its ID
+	pub fn create(&mut self, start: Board) -> GameId {
+		self.next_id += 1;
+		self.games.insert(self.next_id, Game::new(start));
+		self.next_id
+	}
+
+	pub fn get(&self, id: GameId) -> Result<&Game, GameError> {
+		self.games.get(&id).ok_or(GameError::UnknownGame(id))
+	}
+
+	pub fn get_mut(&mut self, id: GameId) -> Result<&mut Game, GameError> {
+		self.games.get_mut(&id).ok_or(GameError::UnknownGame(id))
+	}
+
+	pub fn close(&mut self, id: GameId) -> Result<Game, GameError> {
+		self.games.remove(&id).ok_or(GameError::UnknownGame(id))
+	}
+
+	/// The open games, ordered by ID
+	pub fn list(&self) -> Vec<GameInfo> {
+		self.games
+			.iter()
+			.map(|(&id, game)| GameInfo {
+				id,
+				fen: game.get_board().to_fen(),
+				ply: game.get_cursor(),
+				status: game.status(),
+			})
+			.collect()
+	}
+
+	pub fn len(&self) -> usize {
+		self.games.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.games.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		fen::START_FEN,
+		registry::{GameError, Games},
+		square::Square,
+	};
+
+	#[test]
+	fn test_games_are_independent() {
+		let mut games = Games::new();
+		let a = games.create(Board::new());
+		let b = games.create(Board::new());
+		assert_ne!(a, b);
+		assert_eq!(games.len(), 2);
+
+		games
+			.get_mut(a)
+			.unwrap()
+			.move_piece(Square::E2.index(), Square::E4.index(), None)
+			.unwrap();
+		assert_eq!(games.get(a).unwrap().get_cursor(), 1);
+		assert_eq!(games.get(b).unwrap().get_board(), &Board::new());
+
+		let list = games.list();
+		assert_eq!(list.len(), 2);
+		assert_eq!(list[0].id, a);
+		assert_eq!(list[0].ply, 1);
+		assert_eq!(list[1].fen, START_FEN);
+		assert_eq!(list[1].status, None);
+	}
+
+	#[test]
+	fn test_close() {
+		let mut games = Games::new();
+		let a = games.create(Board::new());
+		assert!(games.close(a).is_ok());
+		assert!(games.is_empty());
+		assert_eq!(games.close(a).unwrap_err(), GameError::UnknownGame(a));
+		assert_eq!(games.get(a).unwrap_err(), GameError::UnknownGame(a));
+
+		// IDs of closed games aren't handed out again
+		let b = games.create(Board::new());
+		assert_ne!(a, b);
+	}
+}
@@ -0,0 +1,270 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum File {
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
+}
+
+impl File {
+	pub const ALL: [File; 8] = [
+		File::A,
+		File::B,
+		File::C,
+		File::D,
+		File::E,
+		File::F,
+		File::G,
+		File::H,
+	];
+
+	/// The file with index `i`, a being 0
+	pub fn new(i: u8) -> Option<File> {
+		Self::ALL.get(i as usize).copied()
+	}
+
+	pub fn index(self) -> u8 {
+		self as u8
+	}
+
+	/// Parses a lowercase letter from 'a' to 'h'
+	pub fn from_char(c: char) -> Option<File> {
+		Self::new((c as u8).wrapping_sub(b'a'))
+	}
+
+	pub fn to_char(self) -> char {
+		(b'a' + self as u8) as char
+	}
+}
+
+impl Display for File {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_char())
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Rank {
+	One,
+	Two,
+	Three,
+	Four,
+	Five,
+	Six,
+	Seven,
+	Eight,
+}
+
+impl Rank {
+	pub const ALL: [Rank; 8] = [
+		Rank::One,
+		Rank::Two,
+		Rank::Three,
+		Rank::Four,
+		Rank::Five,
+		Rank::Six,
+		Rank::Seven,
+		Rank::Eight,
+	];
+
+	/// The rank with index `i`, the first rank being 0
+	pub fn new(i: u8) -> Option<Rank> {
+		Self::ALL.get(i as usize).copied()
+	}
+
+	pub fn index(self) -> u8 {
+		self as u8
+	}
+
+	/// Parses a digit from '1' to '8'
+	pub fn from_char(c: char) -> Option<Rank> {
+		Self::new((c as u8).wrapping_sub(b'1'))
+	}
+
+	pub fn to_char(self) -> char {
+		(b'1' + self as u8) as char
+	}
+}
+
+impl Display for Rank {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_char())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum SquareError {
+	/// Squares are numbered from 0 (a8) to 63 (h1)
+	OutOfRange(u8),
+	InvalidNotation(String),
+}
+
+impl Display for SquareError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SquareError::OutOfRange(i) => write!(f, "square index {i} is not between 0 and 63"),
+			SquareError::InvalidNotation(s) => write!(f, "\"{s}\" is not a square"),
+		}
+	}
+}
+
+impl std::error::Error for SquareError {}
+
+/// One of the 64 squares, numbered row by row from a8 (0) to h1 (63).
+/// It is serialized as that number, which is how the frontend addresses squares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+#[repr(transparent)]
+pub struct Square(u8);
+
+macro_rules! squares {
+	($($name:ident = $i:expr),* $(,)?) => {
+		impl Square {
+			$(pub const $name: Square = Square($i);)*
+		}
+	};
+}
+
+#[rustfmt::skip]
+squares!(
+	A8 = 0, B8 = 1, C8 = 2, D8 = 3, E8 = 4, F8 = 5, G8 = 6, H8 = 7,
+	A7 = 8, B7 = 9, C7 = 10, D7 = 11, E7 = 12, F7 = 13, G7 = 14, H7 = 15,
+	A6 = 16, B6 = 17, C6 = 18, D6 = 19, E6 = 20, F6 = 21, G6 = 22, H6 = 23,
+	A5 = 24, B5 = 25, C5 = 26, D5 = 27, E5 = 28, F5 = 29, G5 = 30, H5 = 31,
+	A4 = 32, B4 = 33, C4 = 34, D4 = 35, E4 = 36, F4 = 37, G4 = 38, H4 = 39,
+	A3 = 40, B3 = 41, C3 = 42, D3 = 43, E3 = 44, F3 = 45, G3 = 46, H3 = 47,
+	A2 = 48, B2 = 49, C2 = 50, D2 = 51, E2 = 52, F2 = 53, G2 = 54, H2 = 55,
+	A1 = 56, B1 = 57, C1 = 58, D1 = 59, E1 = 60, F1 = 61, G1 = 62, H1 = 63,
+);
+
+impl Square {
+	/// The square with index `i`, if it is on the board
+	pub const fn new(i: u8) -> Option<Square> {
+		if i < 64 { Some(Square(i)) } else { None }
+	}
+
+	/// For indices that are known to be on the board, like the bits of a [`crate::bitboard::BitBoard`]
+	pub(crate) const fn new_unchecked(i: u8) -> Square {
+		debug_assert!(i < 64);
+		Square(i)
+	}
+
+	pub const fn from_coords(file: File, rank: Rank) -> Square {
+		Square((7 - rank as u8) * 8 + file as u8)
+	}
+
+	/// Every square, from a8 to h1
+	pub fn all() -> impl Iterator<Item = Square> {
+		(0..64).map(Square)
+	}
+
+	pub const fn index(self) -> u8 {
+		self.0
+	}
+
+	pub fn file(self) -> File {
+		File::ALL[(self.0 % 8) as usize]
+	}
+
+	pub fn rank(self) -> Rank {
+		Rank::ALL[(7 - self.0 / 8) as usize]
+	}
+
+	/// Moves `files` to the right and `ranks` up, as seen from white, as long as that stays on the board
+	pub fn offset(self, files: i8, ranks: i8) -> Option<Square> {
+		let file = File::new((self.file().index() as i8 + files) as u8)?;
+		let rank = Rank::new((self.rank().index() as i8 + ranks) as u8)?;
+		Some(Square::from_coords(file, rank))
+	}
+}
+
+impl TryFrom<u8> for Square {
+	type Error = SquareError;
+
+	fn try_from(value: u8) -> Result<Self, Self::Error> {
+		Square::new(value).ok_or(SquareError::OutOfRange(value))
+	}
+}
+
+impl From<Square> for u8 {
+	fn from(value: Square) -> Self {
+		value.0
+	}
+}
+
+/// Writes the square in algebraic notation, e.g. "e4"
+impl Display for Square {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}{}", self.file(), self.rank())
+	}
+}
+
+impl FromStr for Square {
+	type Err = SquareError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let err = || SquareError::InvalidNotation(s.to_string());
+
+		let mut chars = s.chars();
+		let file = chars.next().and_then(File::from_char).ok_or_else(err)?;
+		let rank = chars.next().and_then(Rank::from_char).ok_or_else(err)?;
+		if chars.next().is_some() {
+			return Err(err());
+		}
+
+		Ok(Square::from_coords(file, rank))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::square::{File, Rank, Square, SquareError};
+
+	#[test]
+	fn test_coords() {
+		assert_eq!(Square::from_coords(File::A, Rank::Eight), Square::A8);
+		assert_eq!(Square::from_coords(File::H, Rank::One), Square::H1);
+		assert_eq!(Square::E4.file(), File::E);
+		assert_eq!(Square::E4.rank(), Rank::Four);
+		assert_eq!(Square::E4.index(), 36);
+
+		assert_eq!(Square::new(63), Some(Square::H1));
+		assert_eq!(Square::new(64), None);
+		assert_eq!(Square::try_from(200), Err(SquareError::OutOfRange(200)));
+	}
+
+	#[test]
+	fn test_offset() {
+		assert_eq!(Square::E2.offset(0, 2), Some(Square::E4));
+		assert_eq!(Square::B1.offset(-1, 2), Some(Square::A3));
+		assert_eq!(Square::H4.offset(1, 0), None);
+		assert_eq!(Square::A8.offset(0, 1), None);
+	}
+
+	#[test]
+	fn test_notation() {
+		assert_eq!("e4".parse(), Ok(Square::E4));
+		assert_eq!("a8".parse(), Ok(Square::A8));
+		assert_eq!(Square::H1.to_string(), "h1");
+		assert_eq!(Square::C6.to_string(), "c6");
+
+		for s in ["", "e", "e9", "i4", "E4", "e44"] {
+			assert_eq!(
+				s.parse::<Square>(),
+				Err(SquareError::InvalidNotation(s.to_string()))
+			);
+		}
+
+		assert!(Square::all().all(|sq| sq.to_string().parse() == Ok(sq)));
+	}
+}
@@ -1,88 +1,397 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
-use std::sync::{LazyLock, RwLock};
+use std::{
+	collections::HashMap,
+	sync::{
+		Arc, LazyLock, Mutex, RwLock,
+		atomic::{AtomicBool, Ordering},
+	},
+	thread,
+};
 
-use chess_rs_lib::{bitboard::BitBoard, board::Board};
+use tauri::{AppHandle, Emitter, Manager, State};
 
-static BOARD_STATE: LazyLock<RwLock<Board>> = LazyLock::new(|| {
-	#[cfg(debug_assertions)]
-	{
-		use chess_rs_lib::piece::{ChessCell, Color, Moved, PieceType};
-
-		let mut b = Board::empty();
-		b.add_piece(
-			ChessCell::with_piece(PieceType::Rook, Color::Black, Moved::No),
-			36,
-		);
-		b.add_piece(
-			ChessCell::with_piece(PieceType::Bishop, Color::Black, Moved::No),
-			35,
-		);
-		b.add_piece(
-			ChessCell::with_piece(PieceType::Bishop, Color::White, Moved::No),
-			0,
-		);
-		b.add_piece(
-			ChessCell::with_piece(PieceType::Bishop, Color::White, Moved::No),
-			7,
-		);
-
-		b.add_piece(
-			ChessCell::with_piece(PieceType::Bishop, Color::Black, Moved::No),
-			63,
-		);
-
-		b.add_piece(
-			ChessCell::with_piece(PieceType::Bishop, Color::Black, Moved::No),
-			63 - 7,
-		);
-		RwLock::new(b)
+use chess_rs_lib::{
+	bitboard::BitBoard,
+	board::Board,
+	computer::{Computer, Strength},
+	eval::{Weights, WeightsError},
+	events::{BoardChanged, EngineInfo, Event, GameOver},
+	game::{Game, History, Outcome},
+	piece::{Color, PieceType},
+	polyglot::{Book, BookError, BookMove},
+	registry::{GameError, GameId, GameInfo, Games},
+	square::Square,
+};
+
+/// The open games, managed by tauri
+type GameState = RwLock<Games>;
+
+/// The computer playing a side of a game
+struct Opponent {
+	/// Locked by the thread searching the computer's move
+	computer: Arc<Mutex<Computer>>,
+	stop: Arc<AtomicBool>,
+	/// Set when the move of the search in progress mustn't be played anymore
+	cancelled: Arc<AtomicBool>,
+}
+
+impl Opponent {
+	fn new(computer: Computer) -> Self {
+		Self {
+			stop: computer.stop_handle(),
+			computer: Arc::new(Mutex::new(computer)),
+			cancelled: Arc::new(AtomicBool::new(false)),
+		}
+	}
+
+	/// Ends the search in progress without playing its move
+	fn cancel(&self) {
+		self.cancelled.store(true, Ordering::SeqCst);
+		self.stop.store(true, Ordering::SeqCst);
+	}
+}
+
+/// The games played against the computer, managed by tauri.
+/// When both are needed, [`GameState`] is locked first
+type OpponentState = Mutex<HashMap<GameId, Opponent>>;
+
+/// The opening book loaded with [`load_book`], if any
+static BOOK: LazyLock<RwLock<Option<Book>>> = LazyLock::new(|| RwLock::new(None));
+
+/// The evaluation weights, replaced by [`load_weights`]
+static WEIGHTS: LazyLock<RwLock<Weights>> = LazyLock::new(|| RwLock::new(Weights::default()));
+
+/// Sends `event` to every window. A window that is gone can't be notified anyway, so failures are only logged
+fn emit<E: Event>(app: &AppHandle, event: E) {
+	if let Err(e) = app.emit(E::NAME, event) {
+		eprintln!("couldn't emit {}: {e}", E::NAME);
 	}
-	#[cfg(not(debug_assertions))]
+}
+
+/// Tells the frontend about the new board of `game` and lets the computer reply if it plays there
+fn board_changed(app: &AppHandle, id: GameId, game: &Game) -> Board {
+	emit(app, BoardChanged::new(id, game));
+	computer_turn(app, id, game);
+	game.get_board().clone()
+}
+
+/// Like [`board_changed`], but also tells the frontend if the move ended the game.
+/// Draws that only can be claimed don't end it, they are left to [`get_status`]
+fn move_played(app: &AppHandle, id: GameId, game: &Game) -> Board {
+	let board = board_changed(app, id, game);
+	if let Some(outcome) = game
+		.status()
+		.filter(|outcome| outcome.reason.is_mandatory())
 	{
-		RwLock::new(Board::new())
+		emit(app, GameOver { game: id, outcome });
 	}
-});
+	board
+}
+
+/// Cancels the computer's search in `id`, whose position is outdated, and starts a new one on a background thread
+/// if it is the computer's turn. Has to be called with [`GameState`] locked, so the board can't change between
+/// checking for cancellation and playing the found move
+fn computer_turn(app: &AppHandle, id: GameId, game: &Game) {
+	let opponents = app.state::<OpponentState>();
+	let mut opponents = opponents.lock().unwrap();
+	let Some(opponent) = opponents.get_mut(&id) else {
+		return;
+	};
+	opponent.cancel();
+	let cancelled = Arc::new(AtomicBool::new(false));
+	opponent.cancelled = cancelled.clone();
+
+	let computer = opponent.computer.clone();
+	let stop = opponent.stop.clone();
+	let game = game.clone();
+	let app = app.clone();
+	thread::spawn(move || {
+		// waits for a cancelled search to return
+		let mut computer = computer.lock().unwrap();
+		// the stop flag is only cleared once no other search can need it anymore. Checking for cancellation afterwards
+		// means a cancel either happened before and is seen here, or sets the flag again for the search below
+		stop.store(false, Ordering::SeqCst);
+		if cancelled.load(Ordering::SeqCst) || !computer.is_turn(&game) {
+			return;
+		}
+		let mv = computer.think(&game, |result| {
+			if !cancelled.load(Ordering::Relaxed) {
+				emit(&app, EngineInfo::new(id, result));
+			}
+		});
+		drop(computer);
+
+		let games = app.state::<GameState>();
+		let mut games = games.write().unwrap();
+		if let Some(mv) = mv
+			&& !cancelled.load(Ordering::Relaxed)
+			&& let Ok(game) = games.get_mut(id)
+		{
+			game.play(mv);
+			move_played(&app, id, game);
+		}
+	});
+}
 
 #[tauri::command]
 fn greet(name: &str) -> String {
 	format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// Opens a new game from the starting position and returns its ID
+#[tauri::command]
+fn create_game(games: State<GameState>) -> GameId {
+	games.write().unwrap().create(Board::new())
+}
+
+#[tauri::command]
+fn list_games(games: State<GameState>) -> Vec<GameInfo> {
+	games.read().unwrap().list()
+}
+
+#[tauri::command]
+fn close_game(
+	games: State<GameState>,
+	opponents: State<OpponentState>,
+	game: GameId,
+) -> Result<(), GameError> {
+	games.write().unwrap().close(game)?;
+	if let Some(opponent) = opponents.lock().unwrap().remove(&game) {
+		opponent.cancel();
+	}
+	Ok(())
+}
+
+/// Lets the computer play `color` in `game` from now on, replacing a previous computer opponent.
+/// It searches its moves on a background thread, so the replies come as events
+#[tauri::command]
+fn play_computer(
+	app: AppHandle,
+	games: State<GameState>,
+	opponents: State<OpponentState>,
+	game: GameId,
+	color: Color,
+	strength: Strength,
+) -> Result<(), GameError> {
+	let games = games.read().unwrap();
+	let current = games.get(game)?;
+
+	let mut computer = Computer::new(color, strength);
+	computer.set_weights(WEIGHTS.read().unwrap().clone());
+	let previous = opponents
+		.lock()
+		.unwrap()
+		.insert(game, Opponent::new(computer));
+	if let Some(previous) = previous {
+		previous.cancel();
+	}
+
+	computer_turn(&app, game, current);
+	Ok(())
+}
+
+/// Cancels the computer's search without playing its move, and lets the user play both sides again
+#[tauri::command]
+fn stop_computer(
+	games: State<GameState>,
+	opponents: State<OpponentState>,
+	game: GameId,
+) -> Result<(), GameError> {
+	let games = games.read().unwrap();
+	games.get(game)?;
+	if let Some(opponent) = opponents.lock().unwrap().remove(&game) {
+		opponent.cancel();
+	}
+	Ok(())
+}
+
+#[tauri::command]
+fn get_board(games: State<GameState>, game: GameId) -> Result<Board, GameError> {
+	Ok(games.read().unwrap().get(game)?.get_board().clone())
+}
+
+#[tauri::command]
+fn get_legal_moves(
+	games: State<GameState>,
+	game: GameId,
+	index: Square,
+) -> Result<BitBoard, GameError> {
+	Ok(games
+		.read()
+		.unwrap()
+		.get(game)?
+		.get_board()
+		.get_legal_moves(index))
+}
+
+/// `promotion` is the piece a pawn reaching the last rank becomes, defaulting to a queen.
+/// The indices aren't [`Square`]s so that out of range ones are reported as a [`MoveError`](chess_rs_lib::moves::MoveError)
+#[tauri::command]
+fn move_piece(
+	app: AppHandle,
+	games: State<GameState>,
+	game: GameId,
+	index: u8,
+	target: u8,
+	promotion: Option<PieceType>,
+) -> Result<Board, GameError> {
+	let mut games = games.write().unwrap();
+	let id = game;
+	let game = games.get_mut(id)?;
+	game.move_piece(index, target, promotion)?;
+	Ok(move_played(&app, id, game))
+}
+
+/// Takes back the last move, if any
+#[tauri::command]
+fn undo(app: AppHandle, games: State<GameState>, game: GameId) -> Result<Board, GameError> {
+	let mut games = games.write().unwrap();
+	let id = game;
+	let game = games.get_mut(id)?;
+	game.undo();
+	Ok(board_changed(&app, id, game))
+}
+
+/// Replays the last move taken back, if any
+#[tauri::command]
+fn redo(app: AppHandle, games: State<GameState>, game: GameId) -> Result<Board, GameError> {
+	let mut games = games.write().unwrap();
+	let id = game;
+	let game = games.get_mut(id)?;
+	game.redo();
+	Ok(board_changed(&app, id, game))
+}
+
+/// Shows the position after the first `ply` moves, staying put if the game is shorter
+#[tauri::command]
+fn goto_ply(
+	app: AppHandle,
+	games: State<GameState>,
+	game: GameId,
+	ply: usize,
+) -> Result<Board, GameError> {
+	let mut games = games.write().unwrap();
+	let id = game;
+	let game = games.get_mut(id)?;
+	game.goto_ply(ply);
+	Ok(board_changed(&app, id, game))
+}
+
+#[tauri::command]
+fn history(games: State<GameState>, game: GameId) -> Result<History, GameError> {
+	Ok(games.read().unwrap().get(game)?.history())
+}
+
+#[tauri::command]
+fn restart(app: AppHandle, games: State<GameState>, game: GameId) -> Result<Board, GameError> {
+	let mut games = games.write().unwrap();
+	let id = game;
+	let game = games.get_mut(id)?;
+	*game = Game::default();
+	Ok(board_changed(&app, id, game))
+}
+
+#[tauri::command]
+fn load_fen(
+	app: AppHandle,
+	games: State<GameState>,
+	game: GameId,
+	fen: &str,
+) -> Result<Board, GameError> {
+	let loaded = Board::from_fen(fen)?;
+	let mut games = games.write().unwrap();
+	let id = game;
+	let game = games.get_mut(id)?;
+	*game = Game::new(loaded);
+	Ok(board_changed(&app, id, game))
+}
+
+#[tauri::command]
+fn get_fen(games: State<GameState>, game: GameId) -> Result<String, GameError> {
+	Ok(games.read().unwrap().get(game)?.get_board().to_fen())
+}
+
+#[tauri::command]
+fn get_status(games: State<GameState>, game: GameId) -> Result<Option<Outcome>, GameError> {
+	Ok(games.read().unwrap().get(game)?.status())
+}
+
+#[tauri::command]
+fn perft(games: State<GameState>, game: GameId, depth: u32) -> Result<u64, GameError> {
+	let mut board = games.read().unwrap().get(game)?.get_board().clone();
+	Ok(board.perft(depth))
+}
+
+/// Loads a Polyglot `.bin` book, replacing the previous one. Returns the number of entries
 #[tauri::command]
-fn get_board() -> Board {
-	BOARD_STATE.read().unwrap().clone()
+fn load_book(path: &str) -> Result<usize, BookError> {
+	let book = Book::open(path)?;
+	let len = book.len();
+	*BOOK.write().unwrap() = Some(book);
+	Ok(len)
 }
 
+/// The book moves for the current position, empty if no book is loaded or the position isn't in it
 #[tauri::command]
-fn get_legal_moves(index: u8) -> BitBoard {
-	BOARD_STATE.read().unwrap().get_legal_moves(index)
+fn get_book_moves(games: State<GameState>, game: GameId) -> Result<Vec<BookMove>, GameError> {
+	let games = games.read().unwrap();
+	let board = games.get(game)?.get_board();
+	Ok(BOOK
+		.read()
+		.unwrap()
+		.as_ref()
+		.map(|book| book.moves(board))
+		.unwrap_or_default())
 }
 
+/// Loads evaluation weights from a JSON file. Terms missing from the file keep their default value
 #[tauri::command]
-fn move_piece(index: u8, target: u8) -> Board {
-	let mut board = BOARD_STATE.write().unwrap();
-	board.move_piece_unchecked(index, target);
-	board.clone()
+fn load_weights(path: &str) -> Result<(), WeightsError> {
+	let weights = Weights::load(path)?;
+	*WEIGHTS.write().unwrap() = weights;
+	Ok(())
 }
 
+/// The static evaluation of the current position in centipawns, positive if white is better
 #[tauri::command]
-fn restart() -> Board {
-	let mut board = BOARD_STATE.write().unwrap();
-	board.reset();
-	board.clone()
+fn get_evaluation(games: State<GameState>, game: GameId) -> Result<i32, GameError> {
+	let games = games.read().unwrap();
+	Ok(WEIGHTS
+		.read()
+		.unwrap()
+		.evaluate(games.get(game)?.get_board()))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
 	tauri::Builder::default()
 		.plugin(tauri_plugin_opener::init())
+		.manage(GameState::new(Games::new()))
+		.manage(OpponentState::default())
 		.invoke_handler(tauri::generate_handler![
 			greet,
+			create_game,
+			list_games,
+			close_game,
+			play_computer,
+			stop_computer,
 			get_board,
 			get_legal_moves,
 			move_piece,
-			restart
+			undo,
+			redo,
+			goto_ply,
+			history,
+			restart,
+			load_fen,
+			get_fen,
+			get_status,
+			perft,
+			load_book,
+			get_book_moves,
+			load_weights,
+			get_evaluation
 		])
 		.run(tauri::generate_context!())
 		.expect("error while running tauri application");
@@ -0,0 +1,494 @@
+//! Speaks the UCI protocol over stdin and stdout, so the engine can be used from chess GUIs and tournament managers
+
+use std::{
+	io::{BufRead, Write},
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	thread::JoinHandle,
+	time::{Duration, Instant},
+};
+
+use chess_rs_lib::{
+	attacks,
+	board::Board,
+	engine::{Engine, SearchLimits, SearchResult},
+	eval::Weights,
+	moves::Move,
+	piece::Color,
+};
+
+const AUTHOR: &str = "the chess-rs developers";
+const DEFAULT_HASH_SIZE_MB: usize = 16;
+const MIN_HASH_SIZE_MB: usize = 1;
+const MAX_HASH_SIZE_MB: usize = 4096;
+/// Kept back from every move's time for the communication with the GUI
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+/// How many moves the remaining time is divided between when the GUI doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+fn main() {
+	let mut uci = Uci::new(std::io::stdout());
+	for line in std::io::stdin().lock().lines() {
+		let Ok(line) = line else {
+			break;
+		};
+		if !uci.handle(&line) {
+			break;
+		}
+	}
+	uci.stop();
+}
+
+/// The parameters of a `go` command
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Go {
+	depth: Option<u32>,
+	nodes: Option<u64>,
+	movetime: Option<Duration>,
+	wtime: Option<Duration>,
+	btime: Option<Duration>,
+	winc: Option<Duration>,
+	binc: Option<Duration>,
+	movestogo: Option<u32>,
+	infinite: bool,
+}
+
+impl Go {
+	fn parse(args: &[&str]) -> Go {
+		let mut go = Go::default();
+		let mut tokens = args.iter().peekable();
+		while let Some(&token) = tokens.next() {
+			if token == "infinite" {
+				go.infinite = true;
+				continue;
+			}
+
+			// every other parameter takes a number. Without one, the next token is a keyword of its own
+			let Some(value) = tokens.peek().and_then(|t| t.parse::<u64>().ok()) else {
+				continue;
+			};
+			tokens.next();
+			let millis = Some(Duration::from_millis(value));
+			match token {
+				"depth" => go.depth = value.try_into().ok(),
+				"nodes" => go.nodes = Some(value),
+				"movetime" => go.movetime = millis,
+				"wtime" => go.wtime = millis,
+				"btime" => go.btime = millis,
+				"winc" => go.winc = millis,
+				"binc" => go.binc = millis,
+				"movestogo" => go.movestogo = value.try_into().ok(),
+				_ => {}
+			}
+		}
+		go
+	}
+
+	/// A fixed move time wins over the clock, and without either the search only ends at its depth or node limit
+	fn limits(&self, col: Color) -> SearchLimits {
+		let (time, inc) = match col {
+			Color::White => (self.wtime, self.winc),
+			Color::Black => (self.btime, self.binc),
+		};
+
+		let time = if self.infinite {
+			None
+		} else if let Some(movetime) = self.movetime {
+			Some(movetime.saturating_sub(MOVE_OVERHEAD))
+		} else {
+			time.map(|remaining| {
+				let moves_to_go = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+				let budget = remaining / moves_to_go + inc.unwrap_or_default() * 3 / 4;
+				budget.min(remaining.saturating_sub(MOVE_OVERHEAD))
+			})
+		};
+
+		SearchLimits {
+			depth: self.depth,
+			nodes: self.nodes,
+			time,
+		}
+	}
+}
+
+struct Uci<W: Write + Send + 'static> {
+	out: Arc<Mutex<W>>,
+	board: Board,
+	/// Hashes of the positions before `board`, for repetition detection
+	history: Vec<u64>,
+	/// `None` while a search runs on another thread, which hands the engine back when it's done
+	engine: Option<Engine>,
+	search: Option<JoinHandle<Engine>>,
+	stop: Arc<AtomicBool>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+	fn new(out: W) -> Self {
+		let engine = Engine::with_hash_size(DEFAULT_HASH_SIZE_MB);
+		Self {
+			out: Arc::new(Mutex::new(out)),
+			board: Board::new(),
+			history: Vec::new(),
+			stop: engine.stop_handle(),
+			engine: Some(engine),
+			search: None,
+		}
+	}
+
+	fn send(&self, line: &str) {
+		send(&self.out, line);
+	}
+
+	/// Handles one line of input. Returns false once the GUI wants the program to quit
+	fn handle(&mut self, line: &str) -> bool {
+		let tokens: Vec<&str> = line.split_whitespace().collect();
+		let Some((&command, args)) = tokens.split_first() else {
+			return true;
+		};
+
+		match command {
+			"uci" => {
+				self.send(concat!(
+					"id name ",
+					env!("CARGO_PKG_NAME"),
+					" ",
+					env!("CARGO_PKG_VERSION")
+				));
+				self.send(&format!("id author {AUTHOR}"));
+				self.send(&format!(
+					"option name Hash type spin default {DEFAULT_HASH_SIZE_MB} min {MIN_HASH_SIZE_MB} max {MAX_HASH_SIZE_MB}"
+				));
+				self.send("option name Weights type string default <empty>");
+				attacks::init();
+				self.send("uciok");
+			}
+			// the GUI waits for this before starting the clock, so slow setup belongs here
+			"isready" => {
+				attacks::init();
+				self.send("readyok");
+			}
+			"ucinewgame" => {
+				self.engine().clear();
+				self.board = Board::new();
+				self.history.clear();
+			}
+			"setoption" => self.set_option(args),
+			"position" => self.set_position(args),
+			"go" => self.go(args),
+			"stop" => self.stop(),
+			"quit" => return false,
+			_ => self.send(&format!("info string unknown command {command}")),
+		}
+		true
+	}
+
+	/// The engine, after waiting for a running search to finish
+	fn engine(&mut self) -> &mut Engine {
+		if let Some(search) = self.search.take() {
+			self.engine = Some(search.join().unwrap());
+		}
+		self.engine.as_mut().unwrap()
+	}
+
+	fn stop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		self.engine();
+	}
+
+	/// `setoption name <name> value <value>`, where both the name and the value may contain spaces
+	fn set_option(&mut self, args: &[&str]) {
+		let value_at = args.iter().position(|&t| t == "value");
+		let name = args
+			.get(1..value_at.unwrap_or(args.len()))
+			.unwrap_or_default()
+			.join(" ");
+		let value = value_at
+			.map(|i| args[i + 1..].join(" "))
+			.unwrap_or_default();
+
+		match name.to_lowercase().as_str() {
+			"hash" => match hash_size(&value) {
+				Some(size) => self.engine().set_hash_size(size),
+				None => self.send(&format!("info string invalid hash size {value}")),
+			},
+			"weights" => {
+				let weights = if value.is_empty() || value == "<empty>" {
+					Ok(Weights::default())
+				} else {
+					Weights::load(&value)
+				};
+				match weights {
+					Ok(weights) => self.engine().set_weights(weights),
+					Err(e) => self.send(&format!("info string {e}")),
+				}
+			}
+			_ => self.send(&format!("info string unknown option {name}")),
+		}
+	}
+
+	/// `position [startpos | fen <fen>] [moves <move>...]`
+	fn set_position(&mut self, args: &[&str]) {
+		let moves_at = args
+			.iter()
+			.position(|&t| t == "moves")
+			.unwrap_or(args.len());
+		let board = match args.first() {
+			Some(&"startpos") => Board::new(),
+			Some(&"fen") => match Board::from_fen(&args[1..moves_at].join(" ")) {
+				Ok(board) => board,
+				Err(e) => {
+					self.send(&format!("info string invalid fen: {e}"));
+					return;
+				}
+			},
+			_ => {
+				self.send("info string expected startpos or fen");
+				return;
+			}
+		};
+
+		self.engine();
+		self.board = board;
+		self.history.clear();
+		for &token in args.iter().skip(moves_at + 1) {
+			let Some(mv) = find_move(&self.board, token) else {
+				self.send(&format!("info string illegal move {token}"));
+				return;
+			};
+			self.history.push(self.board.hash());
+			self.board.make_move(mv);
+		}
+	}
+
+	fn go(&mut self, args: &[&str]) {
+		let go = Go::parse(args);
+		let limits = go.limits(self.board.get_side_to_move());
+
+		let mut engine = self.engine.take().unwrap_or_else(|| {
+			// a search is still running, which the GUI should have stopped first
+			self.stop.store(true, Ordering::Relaxed);
+			self.search.take().unwrap().join().unwrap()
+		});
+		engine.set_previous_positions(self.history.clone());
+		// cleared before the thread starts, so a `stop` right after `go` can't be missed
+		self.stop.store(false, Ordering::Relaxed);
+
+		let mut board = self.board.clone();
+		let out = self.out.clone();
+		let stop = self.stop.clone();
+		self.search = Some(std::thread::spawn(move || {
+			let start = Instant::now();
+			let result = engine.search_with(&mut board, limits, |result| {
+				send(&out, &info(result, start.elapsed()));
+			});
+
+			// in infinite mode the best move may only be sent once the GUI asks for it
+			if go.infinite {
+				while !stop.load(Ordering::Relaxed) {
+					std::thread::sleep(Duration::from_millis(5));
+				}
+			}
+
+			let best = result
+				.get_best_move()
+				.map_or("0000".to_string(), |mv| mv.to_string());
+			send(&out, &format!("bestmove {best}"));
+			engine
+		}));
+	}
+}
+
+fn send<W: Write>(out: &Mutex<W>, line: &str) {
+	let mut out = out.lock().unwrap();
+	// there is nobody left to report a broken pipe to
+	let _ = writeln!(out, "{line}");
+	let _ = out.flush();
+}
+
+/// The transposition table size set with `setoption name Hash`, limited to what the `uci` command advertises
+fn hash_size(value: &str) -> Option<usize> {
+	value
+		.parse::<usize>()
+		.ok()
+		.map(|size| size.clamp(MIN_HASH_SIZE_MB, MAX_HASH_SIZE_MB))
+}
+
+/// Finds the legal move written in long algebraic notation, e.g. "e2e4", "e1g1" or "e7e8q"
+fn find_move(board: &Board, token: &str) -> Option<Move> {
+	board
+		.get_all_legal_moves()
+		.into_iter()
+		.find(|mv| mv.to_string() == token)
+}
+
+fn info(result: &SearchResult, elapsed: Duration) -> String {
+	// a search stopped before depth 1 was completed has no score to report
+	let score = match (result.get_mate(), result.get_score()) {
+		(Some(moves), _) => format!(" score mate {moves}"),
+		(None, Some(cp)) => format!(" score cp {cp}"),
+		(None, None) => String::new(),
+	};
+	let millis = elapsed.as_millis().max(1);
+	let pv: Vec<String> = result.get_pv().iter().map(|mv| mv.to_string()).collect();
+	format!(
+		"info depth {}{score} nodes {} nps {} time {} pv {}",
+		result.get_depth(),
+		result.get_nodes(),
+		result.get_nodes() as u128 * 1000 / millis,
+		elapsed.as_millis(),
+		pv.join(" ")
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		sync::{Arc, Mutex},
+		time::Duration,
+	};
+
+	use chess_rs_lib::{board::Board, piece::Color};
+
+	use crate::{Go, MAX_HASH_SIZE_MB, MIN_HASH_SIZE_MB, Uci, hash_size};
+
+	/// Feeds `script` to a fresh session, waits for the search to finish and returns everything it printed
+	fn run(script: &[&str]) -> Vec<String> {
+		let mut uci = Uci::new(Vec::new());
+		let out: Arc<Mutex<Vec<u8>>> = uci.out.clone();
+		for line in script {
+			assert!(uci.handle(line));
+		}
+		uci.engine();
+
+		let out = out.lock().unwrap();
+		String::from_utf8(out.clone())
+			.unwrap()
+			.lines()
+			.map(str::to_string)
+			.collect()
+	}
+
+	#[test]
+	fn test_handshake() {
+		let out = run(&["uci", "isready"]);
+		assert!(out[0].starts_with("id name chess-rs"));
+		assert!(out[1].starts_with("id author "));
+		assert!(out.iter().any(|l| l.starts_with("option name Hash")));
+		assert_eq!(&out[out.len() - 2..], ["uciok", "readyok"]);
+
+		let mut uci = Uci::new(Vec::new());
+		assert!(!uci.handle("quit"));
+	}
+
+	#[test]
+	fn test_position() {
+		let mut uci = Uci::new(Vec::new());
+		uci.handle("position startpos moves e2e4 e7e5 g1f3");
+		let expected =
+			Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+				.unwrap();
+		assert!(uci.board.is_same_position(&expected));
+		assert_eq!(uci.history.len(), 3);
+
+		uci.handle("position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1 e8c8");
+		let expected = Board::from_fen("2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2").unwrap();
+		assert!(uci.board.is_same_position(&expected));
+
+		// an illegal move keeps the moves before it
+		uci.handle("position startpos moves e2e4 e2e4");
+		assert_eq!(uci.history.len(), 1);
+	}
+
+	#[test]
+	fn test_go() {
+		let out = run(&[
+			"position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+			"go depth 3",
+		]);
+		assert!(out.iter().any(|l| l.contains("score mate 1")));
+		assert_eq!(out.last().unwrap(), "bestmove a1a8");
+
+		let out = run(&["position startpos moves e2e4", "go depth 2"]);
+		assert!(out.iter().any(|l| l.starts_with("info depth 2")));
+		assert!(out.last().unwrap().starts_with("bestmove "));
+
+		let out = run(&[
+			"position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+			"go depth 2",
+		]);
+		assert_eq!(out.last().unwrap(), "bestmove 0000");
+	}
+
+	#[test]
+	fn test_stop() {
+		let mut uci = Uci::new(Vec::new());
+		let out = uci.out.clone();
+		uci.handle("position startpos");
+		uci.handle("go infinite");
+		std::thread::sleep(Duration::from_millis(50));
+		assert!(!String::from_utf8_lossy(&out.lock().unwrap()).contains("bestmove"));
+
+		uci.handle("stop");
+		assert!(String::from_utf8_lossy(&out.lock().unwrap()).contains("bestmove"));
+
+		// a stop arriving before the search thread got going isn't lost
+		let mut uci = Uci::new(Vec::new());
+		let out = uci.out.clone();
+		uci.handle("position startpos");
+		uci.handle("go infinite");
+		uci.handle("stop");
+		assert!(String::from_utf8_lossy(&out.lock().unwrap()).contains("bestmove"));
+	}
+
+	#[test]
+	fn test_time_management() {
+		let go = Go::parse(&[
+			"wtime", "60000", "btime", "1000", "winc", "1000", "binc", "0",
+		]);
+		assert_eq!(
+			go.limits(Color::White).time,
+			Some(Duration::from_millis(60000 / 30 + 750))
+		);
+		assert_eq!(
+			go.limits(Color::Black).time,
+			Some(Duration::from_millis(1000) / 30)
+		);
+
+		let go = Go::parse(&["movetime", "500", "depth", "4"]);
+		assert_eq!(
+			go.limits(Color::White).time,
+			Some(Duration::from_millis(470))
+		);
+		assert_eq!(go.limits(Color::White).depth, Some(4));
+
+		assert_eq!(Go::parse(&["infinite"]).limits(Color::White).time, None);
+	}
+
+	#[test]
+	fn test_go_missing_values() {
+		assert_eq!(Go::parse(&["wtime"]), Go::default());
+
+		let go = Go::parse(&["movetime", "infinite"]);
+		assert!(go.infinite);
+		assert_eq!(go.movetime, None);
+
+		let go = Go::parse(&["infinite", "movetime", "100"]);
+		assert!(go.infinite);
+		assert_eq!(go.movetime, Some(Duration::from_millis(100)));
+
+		let go = Go::parse(&["wtime", "depth", "5", "btime"]);
+		assert_eq!(go.wtime, None);
+		assert_eq!(go.depth, Some(5));
+		assert_eq!(go.btime, None);
+	}
+
+	#[test]
+	fn test_hash_size() {
+		assert_eq!(hash_size("64"), Some(64));
+		assert_eq!(hash_size("0"), Some(MIN_HASH_SIZE_MB));
+		assert_eq!(hash_size("1000000"), Some(MAX_HASH_SIZE_MB));
+		assert_eq!(hash_size("lots"), None);
+	}
+}
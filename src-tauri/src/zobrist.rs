@@ -0,0 +1,201 @@
+use crate::{
+	board::CastlingRights,
+	piece::{ChessCell, Color},
+	square::Square,
+};
+
+/// A random number for every combination of color, piece type and square, plus one per castling right and en passant file.
+/// They are generated at compile time from a fixed seed, so the hash of a position never changes between runs
+struct Keys {
+	pieces: [[[u64; 64]; 6]; 2],
+	castling: [u64; 4],
+	en_passant: [u64; 8],
+	black_to_move: u64,
+}
+
+/// splitmix64, which spreads even a simple counter into well distributed 64-bit numbers
+pub(crate) const fn next_random(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	z ^ (z >> 31)
+}
+
+const KEYS: Keys = {
+	let mut state = 0x5A0B_2157_C0DE_F00D;
+	let mut keys = Keys {
+		pieces: [[[0; 64]; 6]; 2],
+		castling: [0; 4],
+		en_passant: [0; 8],
+		black_to_move: 0,
+	};
+
+	let mut col = 0;
+	while col < 2 {
+		let mut pt = 0;
+		while pt < 6 {
+			let mut sq = 0;
+			while sq < 64 {
+				keys.pieces[col][pt][sq] = next_random(&mut state);
+				sq += 1;
+			}
+			pt += 1;
+		}
+		col += 1;
+	}
+
+	let mut i = 0;
+	while i < 4 {
+		keys.castling[i] = next_random(&mut state);
+		i += 1;
+	}
+
+	let mut i = 0;
+	while i < 8 {
+		keys.en_passant[i] = next_random(&mut state);
+		i += 1;
+	}
+
+	keys.black_to_move = next_random(&mut state);
+	keys
+};
+
+/// The key of `cell` standing on `sq`, 0 for an empty cell
+pub(crate) fn piece_key(cell: ChessCell, sq: Square) -> u64 {
+	let Some(pt) = cell.get_type() else {
+		return 0;
+	};
+	let col = match cell.get_color() {
+		Color::White => 0,
+		Color::Black => 1,
+	};
+	KEYS.pieces[col][pt as usize - 1][sq.index() as usize]
+}
+
+pub(crate) fn castling_key(rights: CastlingRights) -> u64 {
+	let mut key = 0;
+	for (i, right) in [
+		CastlingRights::WHITE_KINGSIDE,
+		CastlingRights::WHITE_QUEENSIDE,
+		CastlingRights::BLACK_KINGSIDE,
+		CastlingRights::BLACK_QUEENSIDE,
+	]
+	.into_iter()
+	.enumerate()
+	{
+		if rights.contains(right) {
+			key ^= KEYS.castling[i];
+		}
+	}
+	key
+}
+
+/// Only the file of the en passant square matters, the rank follows from the side to move
+pub(crate) fn en_passant_key(en_passant: Option<Square>) -> u64 {
+	match en_passant {
+		Some(sq) => KEYS.en_passant[sq.file().index() as usize],
+		None => 0,
+	}
+}
+
+pub(crate) fn side_key(col: Color) -> u64 {
+	match col {
+		Color::White => 0,
+		Color::Black => KEYS.black_to_move,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		board::Board,
+		fen::START_FEN,
+		zobrist::{castling_key, en_passant_key, piece_key, side_key},
+	};
+
+	/// Computes the hash of `board` from scratch, which [`Board::hash`] has to match at all times
+	fn full_hash(board: &Board) -> u64 {
+		let mut hash = side_key(board.get_side_to_move())
+			^ castling_key(board.get_castling_rights())
+			^ en_passant_key(board.get_en_passant());
+		for sq in board.occupancy() {
+			hash ^= piece_key(board[sq], sq);
+		}
+		hash
+	}
+
+	/// Plays every legal move up to `depth` plies deep, checking the incremental hash against a full recomputation on the way
+	fn check_tree(board: &mut Board, depth: u32) {
+		assert_eq!(board.hash(), full_hash(board), "{}", board.to_fen());
+		if depth == 0 {
+			return;
+		}
+
+		for mv in board.get_all_legal_moves() {
+			let before = board.hash();
+			let undo = board.make_move(mv);
+			check_tree(board, depth - 1);
+			board.unmake_move(undo);
+			assert_eq!(board.hash(), before);
+		}
+	}
+
+	#[test]
+	fn test_incremental_hash() {
+		// castling, en passant and promotions all show up within a few plies of these
+		for fen in [
+			START_FEN,
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6 0 1",
+		] {
+			let mut board = Board::from_fen(fen).unwrap();
+			check_tree(&mut board, 2);
+		}
+	}
+
+	#[test]
+	fn test_hash_identifies_positions() {
+		let start = Board::new();
+		assert_eq!(start.hash(), Board::from_fen(START_FEN).unwrap().hash());
+
+		// the same pieces, but with a different side to move, castling rights or en passant square
+		let variants = [
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1",
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+		];
+		for fen in variants {
+			assert_ne!(start.hash(), Board::from_fen(fen).unwrap().hash(), "{fen}");
+		}
+
+		let with_en_passant =
+			Board::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3")
+				.unwrap();
+		let without =
+			Board::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3").unwrap();
+		assert_ne!(with_en_passant.hash(), without.hash());
+
+		// move counters aren't part of the position
+		let later =
+			Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 12 30").unwrap();
+		assert_eq!(start.hash(), later.hash());
+	}
+
+	#[test]
+	fn test_transposition() {
+		let mut a = Board::new();
+		let mut b = Board::new();
+		for (board, moves) in [
+			(&mut a, ["g1f3", "g8f6", "b1c3"]),
+			(&mut b, ["b1c3", "g8f6", "g1f3"]),
+		] {
+			for mv in moves {
+				let mv =
+					board.create_move(mv[..2].parse().unwrap(), mv[2..].parse().unwrap(), None);
+				board.make_move(mv);
+			}
+		}
+		assert_eq!(a.hash(), b.hash());
+	}
+}
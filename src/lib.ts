@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
 export type PieceType = "pawn" | "knight" | "bishop" | "rook" | "queen" | "king";
 export type Color = "black" | "white";
@@ -40,21 +41,230 @@ function convert_board_state(arr: BackendState): BoardState {
 	return board_state;
 }
 
-export async function get_board(): Promise<BoardState> {
-	const res = await invoke("get_board") as BackendState;
+/** Identifies one of the open games, every command about a game takes one */
+export type GameId = number;
+
+export interface GameInfo {
+	id: GameId
+	fen: string
+	/** How many moves are played on the board */
+	ply: number
+	status: Outcome | null
+}
+
+/** Opens a new game and returns its ID */
+export async function create_game(): Promise<GameId> {
+	return await invoke("create_game") as GameId;
+}
+
+export async function list_games(): Promise<GameInfo[]> {
+	return await invoke("list_games") as GameInfo[];
+}
+
+export async function close_game(game: GameId): Promise<void> {
+	await invoke("close_game", { game });
+}
+
+/** How well the computer plays, missing fields keep their default. The search stops at whichever limit comes first */
+export interface Strength {
+	depth?: number
+	nodes?: number
+	/** In milliseconds */
+	time?: number
+	/** How many centipawns worse than the best move the computer's move may be */
+	randomness?: number
+}
+
+/** Lets the computer play `color`, its moves arrive as `board-changed` events */
+export async function play_computer(game: GameId, color: Color, strength: Strength = {}): Promise<void> {
+	const backend_color = color[0].toUpperCase() + color.slice(1);
+	await invoke("play_computer", { game, color: backend_color, strength });
+}
+
+/** Cancels the computer's search and lets the user play both sides again */
+export async function stop_computer(game: GameId): Promise<void> {
+	await invoke("stop_computer", { game });
+}
+
+export async function get_board(game: GameId): Promise<BoardState> {
+	const res = await invoke("get_board", { game }) as BackendState;
+	return convert_board_state(res);
+}
+
+export async function get_legal_moves(game: GameId, index: number): Promise<number[]> {
+	return await invoke("get_legal_moves", { game, index }) as number[];
+}
+
+export type PromotionPiece = "knight" | "bishop" | "rook" | "queen";
+
+/** Why `move_piece` rejected a move, squares are indices like everywhere else */
+export type MoveError =
+	| { OutOfRange: number }
+	| { NoPiece: number }
+	| { NotYourTurn: number }
+	| { IllegalMove: { from: number, to: number } }
+	/** The game ended for good, e.g. by checkmate or the seventy-five-move rule */
+	| { GameOver: Outcome };
+
+/** Why a command about a game failed */
+export type GameError =
+	| { UnknownGame: GameId }
+	| { Move: MoveError }
+	| { Fen: unknown };
+
+/** Rejects with a `GameError` holding a `MoveError` if the move can't be played */
+export async function move_piece(game: GameId, index: number, target: number, promotion?: PromotionPiece): Promise<BoardState> {
+	// the backend expects the piece type as it serializes it, e.g. "Queen"
+	const backend_promotion = promotion ? promotion[0].toUpperCase() + promotion.slice(1) : null;
+	const res = await invoke("move_piece", { game, index, target, promotion: backend_promotion }) as BackendState;
+	return convert_board_state(res);
+}
+
+/** Takes back the last move */
+export async function undo(game: GameId): Promise<BoardState> {
+	const res = await invoke("undo", { game }) as BackendState;
+	return convert_board_state(res);
+}
+
+/** Replays the last move that was taken back */
+export async function redo(game: GameId): Promise<BoardState> {
+	const res = await invoke("redo", { game }) as BackendState;
+	return convert_board_state(res);
+}
+
+/** Shows the position after the first `ply` moves of the game */
+export async function goto_ply(game: GameId, ply: number): Promise<BoardState> {
+	const res = await invoke("goto_ply", { game, ply }) as BackendState;
 	return convert_board_state(res);
 }
 
-export async function get_legal_moves(index: number): Promise<number[]> {
-	return await invoke("get_legal_moves", { index }) as number[];
+export interface HistoryEntry {
+	san: string
+	from: number
+	to: number
+	promotion: string | null
+}
+
+/** `cursor` is how many of the moves are played on the board, the rest were taken back */
+export interface History {
+	moves: HistoryEntry[]
+	cursor: number
+}
+
+export async function history(game: GameId): Promise<History> {
+	return await invoke("history", { game }) as History;
 }
 
-export async function move_piece(index: number, target: number): Promise<BoardState> {
-	const res = await invoke("move_piece", { index, target }) as BackendState;
+export async function restart(game: GameId): Promise<BoardState> {
+	const res = await invoke("restart", { game }) as BackendState;
 	return convert_board_state(res);
 }
 
-export async function restart(): Promise<BoardState> {
-	const res = await invoke("restart") as BackendState;
+export async function load_fen(game: GameId, fen: string): Promise<BoardState> {
+	const res = await invoke("load_fen", { game, fen }) as BackendState;
 	return convert_board_state(res);
 }
+
+export async function get_fen(game: GameId): Promise<string> {
+	return await invoke("get_fen", { game }) as string;
+}
+
+export type GameResult = "White" | "Black" | "Draw";
+export type EndReason =
+	| "Checkmate"
+	| "Stalemate"
+	| "FiftyMoveRule"
+	| "SeventyFiveMoveRule"
+	| "ThreefoldRepetition"
+	| "FivefoldRepetition"
+	| "InsufficientMaterial";
+
+export interface Outcome {
+	result: GameResult
+	reason: EndReason
+}
+
+export async function get_status(game: GameId): Promise<Outcome | null> {
+	return await invoke("get_status", { game }) as Outcome | null;
+}
+
+export async function perft(game: GameId, depth: number): Promise<number> {
+	return await invoke("perft", { game, depth }) as number;
+}
+
+export interface BookMove {
+	from: number
+	to: number
+	promotion: string | null
+	weight: number
+}
+
+/** Loads a Polyglot opening book and returns how many entries it has */
+export async function load_book(path: string): Promise<number> {
+	return await invoke("load_book", { path }) as number;
+}
+
+export async function get_book_moves(game: GameId): Promise<BookMove[]> {
+	return await invoke("get_book_moves", { game }) as BookMove[];
+}
+
+/** Loads evaluation weights from a JSON file, terms missing from it keep their default value */
+export async function load_weights(path: string): Promise<void> {
+	await invoke("load_weights", { path });
+}
+
+/** The evaluation of the current position in centipawns, positive if white is better */
+export async function get_evaluation(game: GameId): Promise<number> {
+	return await invoke("get_evaluation", { game }) as number;
+}
+
+export interface BoardChanged {
+	game: GameId
+	board: BoardState
+	fen: string
+	/** How many moves are played on the board */
+	ply: number
+}
+
+export interface GameOver {
+	game: GameId
+	outcome: Outcome
+}
+
+/** The time both sides have left, in milliseconds. Not sent until the app has a clock */
+export interface ClockTick {
+	game: GameId
+	white: number
+	black: number
+}
+
+/** One iteration of an engine search */
+export interface EngineInfo {
+	game: GameId
+	depth: number
+	/** In centipawns, from the point of view of the side to move, null if the search didn't complete depth 1 */
+	score: number | null
+	/** Moves until mate, negative if the side to move gets mated */
+	mate: number | null
+	nodes: number
+	/** The principal variation in long algebraic notation, e.g. "e7e8q" */
+	pv: string[]
+}
+
+export async function on_board_changed(handler: (event: BoardChanged) => void): Promise<UnlistenFn> {
+	return await listen<Omit<BoardChanged, "board"> & { board: BackendState }>("board-changed", (event) => {
+		handler({ ...event.payload, board: convert_board_state(event.payload.board) });
+	});
+}
+
+export async function on_game_over(handler: (event: GameOver) => void): Promise<UnlistenFn> {
+	return await listen<GameOver>("game-over", (event) => handler(event.payload));
+}
+
+export async function on_clock_tick(handler: (event: ClockTick) => void): Promise<UnlistenFn> {
+	return await listen<ClockTick>("clock-tick", (event) => handler(event.payload));
+}
+
+export async function on_engine_info(handler: (event: EngineInfo) => void): Promise<UnlistenFn> {
+	return await listen<EngineInfo>("engine-info", (event) => handler(event.payload));
+}